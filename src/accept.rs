@@ -1,29 +1,194 @@
-use crate::{error::*, Accept, MediaType};
-use http::StatusCode;
+use crate::{
+    error::*, Accept, AcceptHeaders, AsOffer, MatchKind, MediaType, Negotiation, NonUtf8,
+    QualityList, QualityValue,
+};
 use itertools::Itertools;
 use mime::Mime;
-use std::{cmp::Ordering, fmt, str::FromStr};
+use snafu::ResultExt;
+use std::{
+    cmp::Reverse,
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+/// The media range assumed when the client didn't state any.
+static ANY: MediaType = MediaType {
+    mime: mime::STAR_STAR,
+    weight: None,
+    extensions: Vec::new(),
+    position: 0,
+};
 
 impl Accept {
+    /// An `Accept` that accepts any media type, as a missing Accept header does.
+    pub fn any() -> Self {
+        Self::default()
+    }
+
+    /// Build an `Accept` from the (optional) value of an Accept header, e.g. an
+    /// `http::HeaderValue`. A missing or empty header accepts any media type (RFC 9110
+    /// §12.5.1).
+    pub fn from_header<V: AsRef<[u8]>>(value: Option<V>) -> Result<Self> {
+        Self::from_values(value.as_ref().map(AsRef::as_ref), NonUtf8::Reject)
+    }
+
     /// Determine the most suitable `Content-Type` encoding.
-    pub fn negotiate(&self, available: &[Mime]) -> Result<Mime, StatusCode> {
-        for media_type in &self.types {
-            if available.contains(&media_type.mime) {
-                return Ok(media_type.mime.clone());
-            }
+    ///
+    /// Every available type is weighted by the most specific media range that matches
+    /// it (`text/html;level=1` over `text/html` over `text/*` over `*/*`, see RFC 9110
+    /// §12.5.1), multiplied by the server quality of the offer. The type with the
+    /// highest score wins, the first one in `available` on a tie. A type scoring 0 (e.g.
+    /// because its matching range has `q=0`) is not acceptable and is never chosen. An
+    /// `Accept` without any media range accepts everything, like `*/*`.
+    pub fn negotiate<'a, O: AsOffer>(
+        &'a self,
+        available: &'a [O],
+    ) -> Result<Negotiation<'a, O>, NotAcceptable> {
+        available
+            .iter()
+            .filter_map(|offer| self.match_offer(offer))
+            .reduce(|best, next| {
+                if next.quality > best.quality {
+                    next
+                } else {
+                    best
+                }
+            })
+            .ok_or_else(|| NotAcceptable {
+                offers: available.iter().map(|o| o.mime().clone()).collect(),
+                ranges: self
+                    .types
+                    .iter()
+                    .filter(|range| !range.quality().is_zero())
+                    .cloned()
+                    .collect(),
+            })
+    }
+
+    /// Like [`Accept::negotiate`], but gives the index of the chosen offer in `available`.
+    #[cfg(any(feature = "axum", feature = "axum07", feature = "actix"))]
+    pub(crate) fn negotiate_index<O: AsOffer>(
+        &self,
+        available: &[O],
+    ) -> Result<usize, NotAcceptable> {
+        let negotiation = self.negotiate(available)?;
+        Ok(available
+            .iter()
+            .position(|offer| std::ptr::eq(offer, negotiation.offer))
+            .expect("the negotiated offer is one of the offers"))
+    }
+
+    /// Every acceptable offer, the best first. Offers are ranked like in
+    /// [`Accept::negotiate`], so the first one is what it would choose; offers scoring 0
+    /// are left out.
+    pub fn rank<'a, O: AsOffer>(&'a self, available: &'a [O]) -> Vec<Negotiation<'a, O>> {
+        let mut ranked: Vec<_> = available
+            .iter()
+            .filter_map(|offer| self.match_offer(offer))
+            .collect();
+
+        // a stable sort keeps the order of `available` on a tie
+        ranked.sort_by_key(|n| Reverse(n.quality));
+        ranked
+    }
+
+    /// Build an `Accept` from every Accept field line in `headers`, combined in order as
+    /// if they were sent as one comma-separated list (RFC 9110 §5.3). Fails on the first
+    /// malformed media range, and on a value that isn't valid UTF-8 unless `non_utf8`
+    /// says to skip it.
+    pub fn from_headers<H: AcceptHeaders + ?Sized>(headers: &H, non_utf8: NonUtf8) -> Result<Self> {
+        Self::from_values(headers.accept_values(), non_utf8)
+    }
+
+    /// Like [`Accept::from_headers`], but skips both the values that aren't valid UTF-8
+    /// and the malformed media ranges, returning their errors alongside.
+    pub fn from_headers_lenient<H: AcceptHeaders + ?Sized>(headers: &H) -> (Self, Vec<Error>) {
+        Self::from_values_lenient(headers.accept_values())
+    }
+
+    /// Build an `Accept` from the values of every Accept field line, in order.
+    pub(crate) fn from_values<'a>(
+        values: impl IntoIterator<Item = &'a [u8]>,
+        non_utf8: NonUtf8,
+    ) -> Result<Self> {
+        let mut types = Vec::new();
+        for value in values {
+            let s = match (to_str(value), non_utf8) {
+                (Ok(s), _) => s,
+                (Err(_), NonUtf8::Skip) => continue,
+                (Err(e), NonUtf8::Reject) => return Err(e),
+            };
+            types.extend(QualityList::<MediaType>::parse_elements(s)?);
         }
 
-        if self.wildcard.is_some() {
-            if available.contains(&mime::TEXT_HTML) {
-                return Ok(mime::TEXT_HTML);
-            }
+        Ok(types.into_iter().collect())
+    }
 
-            if let Some(accept) = available.iter().next() {
-                return Ok(accept.clone());
-            }
+    /// Like [`Accept::from_values`], but skips what is malformed.
+    pub(crate) fn from_values_lenient<'a>(
+        values: impl IntoIterator<Item = &'a [u8]>,
+    ) -> (Self, Vec<Error>) {
+        let mut types = Vec::new();
+        let mut errors = Vec::new();
+        for value in values {
+            let s = match to_str(value) {
+                Ok(s) => s,
+                Err(e) => {
+                    errors.push(e);
+                    continue;
+                }
+            };
+
+            let (parsed, skipped) = QualityList::<MediaType>::parse_elements_lenient(s);
+            types.extend(parsed);
+            errors.extend(skipped);
         }
 
-        Err(StatusCode::NOT_ACCEPTABLE)
+        (types.into_iter().collect(), errors)
+    }
+
+    /// Parse an Accept header, skipping the malformed entries instead of failing on the
+    /// first one. The errors of the skipped entries are returned alongside.
+    pub fn parse_lenient(s: &str) -> (Self, Vec<Error>) {
+        let (types, errors) = QualityList::<MediaType>::parse_elements_lenient(s);
+        (types.into_iter().collect(), errors)
+    }
+
+    /// The `*/*` media range, if the client sent one.
+    pub fn wildcard(&self) -> Option<&MediaType> {
+        self.types.iter().find(|range| range.is_wildcard())
+    }
+
+    /// Score `offer` against its most specific matching media range. `None` if it is
+    /// not acceptable.
+    fn match_offer<'a, O: AsOffer>(&'a self, offer: &'a O) -> Option<Negotiation<'a, O>> {
+        let range = self.find_match(offer.mime())?;
+        let quality = range.quality() * offer.quality();
+        let kind = if self.types.is_empty() {
+            MatchKind::Default
+        } else {
+            MatchKind::of(range)
+        };
+
+        (!quality.is_zero()).then_some(Negotiation {
+            offer,
+            range,
+            quality,
+            kind,
+        })
+    }
+
+    /// Find the most specific media range matching `mime`.
+    fn find_match(&self, mime: &Mime) -> Option<&MediaType> {
+        if self.types.is_empty() {
+            return Some(&ANY);
+        }
+
+        self.types
+            .iter()
+            .filter(|range| range.matches(mime))
+            .min_by_key(|range| Reverse(range.specificity()))
     }
 }
 
@@ -31,49 +196,99 @@ impl FromStr for Accept {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut types = Vec::new();
-        let mut wildcard = None;
+        QualityList::<MediaType>::parse_elements(s).map(Self::from_iter)
+    }
+}
 
-        for part in s.split(',') {
-            let mtype: MediaType = part.trim().parse()?;
+impl From<QualityList<MediaType>> for Accept {
+    fn from(list: QualityList<MediaType>) -> Self {
+        list.items.into_iter().collect()
+    }
+}
 
-            if mtype.mime.type_() == mime::STAR {
-                wildcard = Some(mtype);
-            } else {
-                types.push(mtype);
+impl FromIterator<MediaType> for Accept {
+    /// Collect media ranges into a sorted `Accept`, numbering their `position` in
+    /// iteration order. When the same range shows up more than once, the entry with the
+    /// highest weight is kept (the first one on a tie).
+    fn from_iter<T: IntoIterator<Item = MediaType>>(iter: T) -> Self {
+        let mut types: Vec<MediaType> = Vec::new();
+
+        for (position, mut mtype) in iter.into_iter().enumerate() {
+            mtype.position = position;
+            match types.iter_mut().find(|t| t.is_same_range(&mtype.mime)) {
+                Some(existing) if existing.quality() < mtype.quality() => *existing = mtype,
+                Some(_) => {}
+                None => types.push(mtype),
             }
         }
 
-        types.sort_by(|a, b| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+        types.sort_by(|a, b| b.cmp(a));
 
-        Ok(Accept { wildcard, types })
+        Accept { types }
     }
 }
 
 impl From<Mime> for Accept {
     fn from(mime: Mime) -> Self {
         Self {
-            wildcard: None,
             types: vec![MediaType::from(mime)],
         }
     }
 }
 
+impl PartialEq for Accept {
+    /// The ranges are already in order of precedence, so their `position` is left out.
+    fn eq(&self, other: &Self) -> bool {
+        self.types.len() == other.types.len()
+            && self
+                .types
+                .iter()
+                .zip(&other.types)
+                .all(|(a, b)| a.identity() == b.identity())
+    }
+}
+
+impl Eq for Accept {}
+
+impl Hash for Accept {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for range in &self.types {
+            range.identity().hash(state);
+        }
+    }
+}
+
 impl fmt::Display for Accept {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.types.iter().map(|m| m.to_string()).join(", "))?;
+        write!(f, "{}", self.types.iter().map(|m| m.to_string()).join(", "))
+    }
+}
 
-        if let Some(wildcard) = &self.wildcard {
-            write!(f, ", {wildcard}")?;
+#[cfg(any(feature = "axum", feature = "axum07", feature = "actix"))]
+impl crate::MalformedAccept {
+    /// Build an `Accept` from the values of every Accept field line, handling a
+    /// malformed header as configured.
+    pub(crate) fn accept<H: AcceptHeaders + ?Sized>(self, headers: &H) -> Result<Accept> {
+        match self {
+            Self::Reject => Accept::from_headers(headers, NonUtf8::Reject),
+            Self::Skip => Ok(Accept::from_headers_lenient(headers).0),
+            Self::Ignore => Ok(Accept::from_headers(headers, NonUtf8::Reject).unwrap_or_default()),
         }
-
-        Ok(())
     }
 }
 
+/// The text of a header value.
+fn to_str(value: &[u8]) -> Result<&str> {
+    std::str::from_utf8(value).context(HeaderValueSnafu {
+        value: String::from_utf8_lossy(value),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{Offer, QValue};
+    use std::collections::{BTreeSet, HashSet};
 
     #[test]
     fn accept_should_be_parsed_and_sorted() {
@@ -82,19 +297,151 @@ mod tests {
             .unwrap();
 
         assert_eq!(
-            accept.wildcard,
-            Some(MediaType::from_str("*/*; q=0.6").unwrap())
+            accept.wildcard(),
+            Some(&MediaType {
+                position: 3,
+                ..MediaType::from_str("*/*; q=0.7").unwrap()
+            })
         );
-        assert_eq!(accept.types.len(), 3);
+        assert_eq!(accept.types.len(), 4);
         assert_eq!(
             accept.types[0].mime,
             Mime::from_str("application/json").unwrap()
         );
         assert_eq!(accept.types[0].weight, None);
         assert_eq!(accept.types[1].mime, Mime::from_str("text/html").unwrap());
-        assert_eq!(accept.types[1].weight, Some(0.9));
+        assert_eq!(accept.types[1].weight, "0.9".parse().ok());
         assert_eq!(accept.types[2].mime, Mime::from_str("text/plain").unwrap());
-        assert_eq!(accept.types[2].weight, Some(0.8));
+        assert_eq!(accept.types[2].weight, "0.8".parse().ok());
+    }
+
+    #[test]
+    fn equally_preferred_ranges_should_keep_header_order() {
+        let accept = "text/plain, application/json, image/png;q=0.5, text/html, image/*;q=0.5"
+            .parse::<Accept>()
+            .unwrap();
+
+        assert_eq!(
+            accept.to_string(),
+            "text/plain, application/json, text/html, image/png;q=0.5, image/*;q=0.5"
+        );
+        let positions: Vec<_> = accept.types.iter().map(|t| t.position).collect();
+        assert_eq!(positions, vec![0, 1, 3, 2, 4]);
+        assert_eq!(accept.to_string().parse::<Accept>().unwrap(), accept);
+        let a: Accept = "application/json, text/html;q=0.9".parse().unwrap();
+        let b: Accept = "text/html;q=0.9, application/json".parse().unwrap();
+        assert_eq!(a, b);
+        assert_eq!(HashSet::from([a, b]).len(), 1);
+
+        let sorted: BTreeSet<_> = accept.types.iter().cloned().collect();
+        assert!(sorted.into_iter().rev().eq(accept.types));
+    }
+
+    #[test]
+    fn duplicate_ranges_should_be_merged() {
+        let accept = "*/*;q=0.1, text/plain;format=fixed;q=0.4, text/plain;format=flowed, \
+                      */*;q=0.5, text/plain;format=fixed;q=0.2"
+            .parse::<Accept>()
+            .unwrap();
+
+        assert_eq!(
+            accept.to_string(),
+            "text/plain;format=flowed, */*;q=0.5, text/plain;format=fixed;q=0.4"
+        );
+        assert_eq!(accept.wildcard().unwrap().weight, "0.5".parse().ok());
+    }
+
+    #[test]
+    fn accept_with_quoted_commas_should_be_parsed() {
+        let accept = r#"application/ld+json;profile="https://a, https://b";q=0.9, application/problem+json;x="a\, b", text/html"#
+            .parse::<Accept>()
+            .unwrap();
+
+        assert_eq!(accept.types.len(), 3);
+        assert_eq!(
+            accept.types[0].mime.essence_str(),
+            "application/problem+json"
+        );
+        assert_eq!(accept.types[0].mime.get_param("x").unwrap(), "a, b");
+        assert_eq!(accept.types[1].mime, mime::TEXT_HTML);
+        assert_eq!(
+            accept.types[2].mime.get_param("profile").unwrap(),
+            "https://a, https://b"
+        );
+    }
+
+    #[test]
+    fn escaped_quotes_in_parameters_should_be_reported() {
+        let header = r#"application/json;profile="say \"hi\"", text/html;x="a\\b""#;
+        let err = header.parse::<Accept>().unwrap_err();
+        assert!(matches!(err, Error::UnsupportedParameter { .. }));
+        assert_eq!(
+            err.to_string(),
+            r#"Unsupported parameter value (a media type can't hold a '"'): say "hi""#
+        );
+
+        let (accept, errors) = Accept::parse_lenient(header);
+        assert_eq!(accept.to_string(), r#"text/html;x="a\\b""#);
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0], Error::UnsupportedParameter { .. }));
+    }
+
+    #[test]
+    fn accept_should_ignore_empty_elements() {
+        let accept = "text/html,,application/json".parse::<Accept>().unwrap();
+        assert_eq!(accept.types, vec![mime::TEXT_HTML, mime::APPLICATION_JSON]);
+
+        // the examples from RFC 9110 §5.6.1.2
+        let accept = "text/html ,text/plain,".parse::<Accept>().unwrap();
+        assert_eq!(accept.types, vec![mime::TEXT_HTML, mime::TEXT_PLAIN]);
+
+        let accept = "text/html , ,text/plain,text/csv"
+            .parse::<Accept>()
+            .unwrap();
+        assert_eq!(
+            accept.types,
+            vec![mime::TEXT_HTML, mime::TEXT_PLAIN, mime::TEXT_CSV]
+        );
+    }
+
+    #[test]
+    fn lenient_parsing_should_skip_malformed_entries() {
+        let (accept, errors) = Accept::parse_lenient("text/html, foo, */*;q=0.5, text/plain;q=2");
+
+        assert_eq!(accept.types, vec![mime::TEXT_HTML, mime::STAR_STAR]);
+        assert_eq!(accept.wildcard().unwrap().to_string(), "*/*;q=0.5");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].to_string(), "Invalid media type: foo");
+        assert_eq!(errors[1].to_string(), "Weight should be 0.0-1.0. Got 2");
+
+        assert!("text/html, foo, */*".parse::<Accept>().is_err());
+    }
+
+    #[test]
+    fn missing_or_empty_accept_should_accept_anything() {
+        let available = &[mime::APPLICATION_JSON, mime::TEXT_PLAIN];
+
+        for accept in [
+            Accept::any(),
+            Accept::default(),
+            "".parse().unwrap(),
+            " , ".parse().unwrap(),
+            Accept::from_header(None::<&str>).unwrap(),
+            Accept::from_header(Some("")).unwrap(),
+        ] {
+            assert_eq!(accept, Accept::any());
+            let negotiated = accept.negotiate(&available[..]).unwrap();
+            assert_eq!(negotiated.mime(), &mime::APPLICATION_JSON);
+        }
+
+        let accept = Accept::from_header(Some("text/plain")).unwrap();
+        assert_eq!(accept.types, vec![mime::TEXT_PLAIN]);
+        assert_eq!(
+            Accept::from_header(Some(b"text/\xff"))
+                .unwrap_err()
+                .to_string(),
+            "Header value is not valid UTF-8: \"text/\u{fffd}\""
+        );
     }
 
     #[test]
@@ -110,11 +457,228 @@ mod tests {
 
         let negotiated = accept.negotiate(&available[..]).unwrap();
 
-        assert_eq!(negotiated, Mime::from_str("application/json").unwrap());
+        assert_eq!(
+            negotiated.mime(),
+            &Mime::from_str("application/json").unwrap()
+        );
+
+        let available = &[Mime::from_str("application/xml").unwrap()];
+        let negotiated = accept.negotiate(&available[..]).unwrap();
+        assert_eq!(
+            negotiated.mime(),
+            &Mime::from_str("application/xml").unwrap()
+        );
+    }
+
+    #[test]
+    fn content_negotiation_should_honor_subtype_wildcards() {
+        let accept = "application/json;q=0.5, text/*;q=0.8, text/csv;q=0.2, */*;q=0.1"
+            .parse::<Accept>()
+            .unwrap();
+
+        let available = &[mime::APPLICATION_JSON, mime::TEXT_PLAIN];
+        let negotiated = accept.negotiate(&available[..]).unwrap();
+        assert_eq!(negotiated.mime(), &mime::TEXT_PLAIN);
+
+        // text/csv is covered by its own, lower ranked entry rather than by text/*
+        let available = &[mime::TEXT_CSV, mime::APPLICATION_JSON];
+        let negotiated = accept.negotiate(&available[..]).unwrap();
+        assert_eq!(negotiated.mime(), &mime::APPLICATION_JSON);
+
+        let available = &[mime::TEXT_CSV, mime::IMAGE_PNG];
+        let negotiated = accept.negotiate(&available[..]).unwrap();
+        assert_eq!(negotiated.mime(), &mime::TEXT_CSV);
+
+        let available = &[mime::IMAGE_PNG];
+        let negotiated = accept.negotiate(&available[..]).unwrap();
+        assert_eq!(negotiated.mime(), &mime::IMAGE_PNG);
+    }
+
+    #[test]
+    fn content_negotiation_should_prefer_offer_order_on_tie() {
+        let accept = "text/*, text/html".parse::<Accept>().unwrap();
+
+        let available = &[mime::TEXT_PLAIN, mime::TEXT_HTML];
+        let negotiated = accept.negotiate(&available[..]).unwrap();
+        assert_eq!(negotiated.mime(), &mime::TEXT_PLAIN);
+
+        let available = &[mime::TEXT_HTML, mime::TEXT_PLAIN];
+        let negotiated = accept.negotiate(&available[..]).unwrap();
+        assert_eq!(negotiated.mime(), &mime::TEXT_HTML);
+
+        let available = &[mime::IMAGE_PNG];
+        let negotiated = accept.negotiate(&available[..]);
+        assert_eq!(negotiated.unwrap_err().status_code(), 406);
+    }
+
+    #[test]
+    fn content_negotiation_should_follow_rfc_precedence() {
+        // the example from RFC 9110 §12.5.1
+        let accept = "text/*;q=0.3, text/plain;q=0.7, text/plain;format=flowed, \
+                      text/plain;format=fixed;q=0.4, */*;q=0.5"
+            .parse::<Accept>()
+            .unwrap();
+
+        let expected = [
+            ("text/plain;format=flowed", "1"),
+            ("text/plain", "0.7"),
+            ("text/html", "0.3"),
+            ("image/jpeg", "0.5"),
+            ("text/plain;format=fixed", "0.4"),
+            ("text/html;level=3", "0.3"),
+        ];
+        for (offer, quality) in expected {
+            let mime = Mime::from_str(offer).unwrap();
+            let range = accept.find_match(&mime).unwrap();
+            assert_eq!(range.quality(), quality.parse().unwrap(), "{offer}");
+        }
+
+        let available = &[
+            Mime::from_str("text/plain;format=fixed").unwrap(),
+            Mime::from_str("text/plain").unwrap(),
+            Mime::from_str("text/plain;format=flowed").unwrap(),
+        ];
+        let negotiated = accept.negotiate(&available[..]).unwrap();
+        assert_eq!(negotiated.mime(), &available[2]);
+    }
+
+    #[test]
+    fn content_negotiation_should_weigh_server_quality() {
+        let available = &[
+            Offer::from(mime::APPLICATION_JSON),
+            Offer::new(mime::TEXT_CSV, 0.5).unwrap(),
+            Offer::new(mime::TEXT_HTML, 0.0).unwrap(),
+        ];
+
+        let accept = "*/*".parse::<Accept>().unwrap();
+        let negotiated = accept.negotiate(&available[..]).unwrap();
+        assert_eq!(negotiated.mime(), &mime::APPLICATION_JSON);
+
+        // json scores 0.8 * 1.0, csv 1.0 * 0.5
+        let accept = "application/json;q=0.8, text/csv"
+            .parse::<Accept>()
+            .unwrap();
+        let negotiated = accept.negotiate(&available[..]).unwrap();
+        assert_eq!(negotiated.mime(), &mime::APPLICATION_JSON);
+
+        let accept = "application/json;q=0.4, text/csv"
+            .parse::<Accept>()
+            .unwrap();
+        let negotiated = accept.negotiate(&available[..]).unwrap();
+        assert_eq!(negotiated.mime(), &mime::TEXT_CSV);
+
+        let accept = "text/html".parse::<Accept>().unwrap();
+        let negotiated = accept.negotiate(&available[..]);
+        assert_eq!(negotiated.unwrap_err().status_code(), 406);
+
+        assert!(Offer::new(mime::TEXT_HTML, 1.5).is_err());
+        assert!(matches!(
+            Offer::new(mime::TEXT_CSV, 0.0004),
+            Err(Error::ParseWeight { .. })
+        ));
+    }
+
+    #[test]
+    fn content_negotiation_should_skip_excluded_types() {
+        let accept = "*/*, application/xml;q=0".parse::<Accept>().unwrap();
+
+        let available = &[mime::TEXT_XML, "application/xml".parse().unwrap()];
+        let negotiated = accept.negotiate(&available[..]).unwrap();
+        assert_eq!(negotiated.mime(), &mime::TEXT_XML);
 
         let available = &[Mime::from_str("application/xml").unwrap()];
+        let negotiated = accept.negotiate(&available[..]);
+        assert_eq!(negotiated.unwrap_err().status_code(), 406);
+
+        let accept = "application/json, text/*;q=0, */*;q=0"
+            .parse::<Accept>()
+            .unwrap();
+
+        let available = &[mime::TEXT_HTML, mime::IMAGE_PNG];
+        let negotiated = accept.negotiate(&available[..]);
+        assert_eq!(negotiated.unwrap_err().status_code(), 406);
+
+        let available = &[mime::TEXT_HTML, mime::APPLICATION_JSON];
         let negotiated = accept.negotiate(&available[..]).unwrap();
-        assert_eq!(negotiated, Mime::from_str("application/xml").unwrap());
+        assert_eq!(negotiated.mime(), &mime::APPLICATION_JSON);
+    }
+
+    #[test]
+    fn content_negotiation_should_report_the_match() {
+        let accept = "application/json;q=0.9, text/*;q=0.5, image/png;q=0, */*;q=0.1"
+            .parse::<Accept>()
+            .unwrap();
+        let cases = [
+            (
+                mime::APPLICATION_JSON,
+                "application/json;q=0.9",
+                MatchKind::Exact,
+            ),
+            (mime::TEXT_PLAIN, "text/*;q=0.5", MatchKind::SubtypeWildcard),
+            (mime::IMAGE_GIF, "*/*;q=0.1", MatchKind::FullWildcard),
+        ];
+        for (mime, range, kind) in cases {
+            let available = [Offer::new(mime.clone(), 0.5).unwrap()];
+            let negotiated = accept.negotiate(&available).unwrap();
+            assert_eq!(negotiated.mime(), &mime);
+            assert_eq!(negotiated.range.to_string(), range);
+            assert_eq!(
+                negotiated.quality,
+                negotiated.range.quality() * "0.5".parse().unwrap()
+            );
+            assert_eq!(negotiated.kind, kind);
+        }
+
+        let available = [mime::TEXT_CSV];
+        let accept = Accept::any();
+        let negotiated = accept.negotiate(&available).unwrap();
+        assert_eq!(negotiated.offer, &mime::TEXT_CSV);
+        assert_eq!(negotiated.range, &"*/*".parse::<MediaType>().unwrap());
+        assert_eq!(negotiated.quality, QValue::ONE);
+        assert_eq!(negotiated.kind, MatchKind::Default);
+
+        let accept = "application/json, image/png;q=0".parse::<Accept>().unwrap();
+        let available = [mime::IMAGE_PNG, mime::TEXT_CSV];
+        let err = accept.negotiate(&available).unwrap_err();
+        assert_eq!(err.offers, available);
+        assert_eq!(err.ranges, vec![mime::APPLICATION_JSON]);
+        assert_eq!(
+            err.to_string(),
+            "None of image/png, text/csv is acceptable to application/json"
+        );
+    }
+
+    #[test]
+    fn offers_should_be_ranked() {
+        let accept = "application/json;q=0.5, text/*;q=0.8, text/csv;q=0, */*;q=0.1"
+            .parse::<Accept>()
+            .unwrap();
+        let available = [
+            Offer::from(mime::IMAGE_PNG),
+            Offer::from(mime::APPLICATION_JSON),
+            Offer::from(mime::TEXT_CSV),
+            Offer::new(mime::TEXT_HTML, 0.5).unwrap(),
+            Offer::from(mime::TEXT_PLAIN),
+            Offer::from(mime::IMAGE_GIF),
+        ];
+
+        let ranked = accept.rank(&available);
+        let mimes: Vec<_> = ranked.iter().map(|n| n.mime().clone()).collect();
+        assert_eq!(
+            mimes,
+            vec![
+                mime::TEXT_PLAIN,
+                mime::APPLICATION_JSON,
+                mime::TEXT_HTML,
+                mime::IMAGE_PNG,
+                mime::IMAGE_GIF
+            ]
+        );
+        assert_eq!(ranked[0], accept.negotiate(&available).unwrap());
+        assert_eq!(ranked[2].quality, "0.4".parse().unwrap());
+
+        let accept = "application/json".parse::<Accept>().unwrap();
+        assert!(accept.rank(&available[..1]).is_empty());
     }
 
     #[test]
@@ -125,7 +689,7 @@ mod tests {
 
         let available = &[Mime::from_str("application/xml").unwrap()];
         let negotiated = accept.negotiate(&available[..]);
-        assert_eq!(negotiated, Err(StatusCode::NOT_ACCEPTABLE));
+        assert_eq!(negotiated.unwrap_err().status_code(), 406);
     }
 
     #[test]
@@ -136,7 +700,7 @@ mod tests {
 
         assert_eq!(
             accept.to_string(),
-            "application/json, text/html;q=0.9, text/plain;q=0.8, */*;q=0.6"
+            "application/json, text/html;q=0.9, text/plain;q=0.8, */*;q=0.7"
         );
     }
 }
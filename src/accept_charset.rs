@@ -0,0 +1,142 @@
+use crate::{
+    error::*,
+    parse::{is_token, quote},
+    AcceptCharset, Charset, QValue, QualityValue,
+};
+use mime::Mime;
+use snafu::ensure;
+use std::{fmt, fmt::Write, str::FromStr};
+
+impl AcceptCharset {
+    /// Choose a charset from the ones the server supports, the first in `available` on
+    /// a tie. `*` stands for every charset not listed on its own, and a charset weighted
+    /// 0 is never chosen. Without any entry, every charset is acceptable. `None` when
+    /// nothing is acceptable.
+    pub fn negotiate<'a>(&self, available: &[&'a str]) -> Option<&'a str> {
+        available
+            .iter()
+            .filter_map(|charset| Some((*charset, self.quality(charset)?)))
+            .filter(|(_, quality)| !quality.is_zero())
+            .reduce(|best, next| if next.1 > best.1 { next } else { best })
+            .map(|(charset, _)| charset)
+    }
+
+    /// Negotiate a charset and set it as the `charset` parameter of `mime`, e.g. the
+    /// media type chosen by [`Accept::negotiate`](crate::Accept::negotiate). An existing
+    /// `charset` parameter is replaced. `None` when no charset is acceptable.
+    pub fn negotiate_mime(&self, mime: &Mime, available: &[&str]) -> Option<Mime> {
+        let charset = self.negotiate(available)?;
+
+        let mut source = mime.essence_str().to_owned();
+        for (name, value) in mime.params().filter(|(name, _)| *name != mime::CHARSET) {
+            write!(source, ";{name}={}", quote(value.as_str())).expect("write to string");
+        }
+        write!(source, ";charset={}", quote(charset)).expect("write to string");
+
+        source.parse().ok()
+    }
+
+    /// The weight of `charset`: the one of its own entry, else the one of `*`.
+    fn quality(&self, charset: &str) -> Option<QValue> {
+        if self.items.is_empty() {
+            return Some(QValue::ONE);
+        }
+
+        self.items
+            .iter()
+            .find(|c| c.value.as_str().eq_ignore_ascii_case(charset))
+            .or_else(|| self.items.iter().find(|c| c.value.as_str() == "*"))
+            .map(QualityValue::quality)
+    }
+}
+
+impl Charset {
+    /// The charset as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Charset {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ensure!(is_token(s), TokenSnafu { value: s });
+
+        Ok(Self(s.to_owned()))
+    }
+}
+
+impl fmt::Display for Charset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Accept;
+
+    #[test]
+    fn accept_charset_should_be_parsed_and_sorted() {
+        let accept = "iso-8859-5;q=0.5, unicode-1-1;q=0.8, *;q=0.1, UTF-8"
+            .parse::<AcceptCharset>()
+            .unwrap();
+
+        assert_eq!(
+            accept.to_string(),
+            "UTF-8, unicode-1-1;q=0.8, iso-8859-5;q=0.5, *;q=0.1"
+        );
+        assert!("utf 8".parse::<AcceptCharset>().is_err());
+        assert!("utf-8;q=-1".parse::<AcceptCharset>().is_err());
+    }
+
+    #[test]
+    fn charset_should_be_negotiated() {
+        let accept = "iso-8859-5, unicode-1-1;q=0.8"
+            .parse::<AcceptCharset>()
+            .unwrap();
+        assert_eq!(
+            accept.negotiate(&["utf-8", "Unicode-1-1"]),
+            Some("Unicode-1-1")
+        );
+        assert_eq!(accept.negotiate(&["utf-8"]), None);
+
+        let accept = "utf-8;q=0.5, iso-8859-1;q=0, *;q=0.6"
+            .parse::<AcceptCharset>()
+            .unwrap();
+        assert_eq!(accept.negotiate(&["utf-8", "us-ascii"]), Some("us-ascii"));
+        assert_eq!(accept.negotiate(&["ISO-8859-1"]), None);
+
+        let accept = AcceptCharset::default();
+        assert_eq!(accept.negotiate(&["utf-8", "us-ascii"]), Some("utf-8"));
+    }
+
+    #[test]
+    fn charset_should_be_set_on_negotiated_mime() {
+        let accept = "text/html;level=1, application/json;q=0.5"
+            .parse::<Accept>()
+            .unwrap();
+        let charset = "iso-8859-5, utf-8;q=0.8".parse::<AcceptCharset>().unwrap();
+
+        let available = ["text/html;level=1;charset=us-ascii"
+            .parse::<Mime>()
+            .unwrap()];
+        let negotiated = accept.negotiate(&available).unwrap();
+        let mime = charset
+            .negotiate_mime(negotiated.mime(), &["utf-8", "iso-8859-5"])
+            .unwrap();
+        assert_eq!(mime.to_string(), "text/html;level=1;charset=iso-8859-5");
+        assert_eq!(mime.get_param(mime::CHARSET).unwrap(), "iso-8859-5");
+
+        let mime = charset
+            .negotiate_mime(&mime::APPLICATION_JSON, &["utf-8"])
+            .unwrap();
+        assert_eq!(mime.to_string(), "application/json;charset=utf-8");
+        assert_eq!(
+            charset.negotiate_mime(&mime::TEXT_HTML, &["us-ascii"]),
+            None
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{error::*, parse::is_token, AcceptEncoding, Coding, QualityItem, QualityValue};
+use snafu::ensure;
+use std::{fmt, str::FromStr};
+
+const IDENTITY: &str = "identity";
+
+impl AcceptEncoding {
+    /// An `AcceptEncoding` that accepts any content coding, as a missing
+    /// Accept-Encoding header does. Note that an *empty* header is different: it only
+    /// accepts `identity`.
+    pub fn any() -> Self {
+        Self {
+            items: vec![QualityItem {
+                value: Coding("*".to_owned()),
+                weight: None,
+            }],
+        }
+    }
+
+    /// Choose the content coding to apply from the ones the server supports, the first
+    /// in `available` on a tie. `identity` is acceptable unless the client excluded it
+    /// with `identity;q=0` or `*;q=0`, and it is only chosen without an explicit
+    /// preference when no other coding is acceptable (RFC 9110 §12.5.3). `None` when
+    /// nothing is acceptable.
+    pub fn negotiate<'a>(&self, available: &[&'a str]) -> Option<&'a str> {
+        let best = available
+            .iter()
+            .filter_map(|coding| Some((*coding, self.find_match(coding)?.quality())))
+            .filter(|(_, quality)| !quality.is_zero())
+            .reduce(|best, next| if next.1 > best.1 { next } else { best });
+
+        best.map(|(coding, _)| coding).or_else(|| {
+            available
+                .iter()
+                .find(|coding| coding.eq_ignore_ascii_case(IDENTITY))
+                .filter(|coding| self.find_match(coding).is_none())
+                .copied()
+        })
+    }
+
+    /// The entry for `coding`, or `*` when it isn't listed on its own.
+    fn find_match(&self, coding: &str) -> Option<&QualityItem<Coding>> {
+        self.items
+            .iter()
+            .find(|c| c.value.matches(coding))
+            .or_else(|| self.items.iter().find(|c| c.value.as_str() == "*"))
+    }
+}
+
+impl Coding {
+    /// The content coding as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Check whether this is the content coding `coding`, ignoring case and treating
+    /// `x-gzip` and `x-compress` as `gzip` and `compress` (RFC 9110 §8.4.1). `*` isn't
+    /// considered to be any coding.
+    pub fn matches(&self, coding: &str) -> bool {
+        canonical(self.as_str()).eq_ignore_ascii_case(canonical(coding))
+    }
+}
+
+impl FromStr for Coding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ensure!(is_token(s), TokenSnafu { value: s });
+
+        Ok(Self(s.to_owned()))
+    }
+}
+
+impl fmt::Display for Coding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Map the legacy `x-` aliases to their registered content coding.
+fn canonical(coding: &str) -> &str {
+    if coding.eq_ignore_ascii_case("x-gzip") {
+        "gzip"
+    } else if coding.eq_ignore_ascii_case("x-compress") {
+        "compress"
+    } else {
+        coding
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_encoding_should_be_parsed_and_sorted() {
+        let accept = "gzip;q=1.0, br, identity;q=0, *;q=0"
+            .parse::<AcceptEncoding>()
+            .unwrap();
+
+        assert_eq!(accept.to_string(), "gzip;q=1, br, identity;q=0, *;q=0");
+        assert_eq!(
+            "gzip, b r"
+                .parse::<AcceptEncoding>()
+                .unwrap_err()
+                .to_string(),
+            "Invalid token: b r"
+        );
+        assert!("gzip;q=2".parse::<AcceptEncoding>().is_err());
+        assert!("gzip;level=1".parse::<AcceptEncoding>().is_err());
+    }
+
+    #[test]
+    fn encoding_should_be_negotiated() {
+        let accept = "gzip;q=0.8, br, identity;q=0, *;q=0"
+            .parse::<AcceptEncoding>()
+            .unwrap();
+        assert_eq!(accept.negotiate(&["gzip", "br"]), Some("br"));
+        assert_eq!(accept.negotiate(&["deflate", "gzip"]), Some("gzip"));
+        assert_eq!(accept.negotiate(&["deflate", "identity"]), None);
+
+        let accept = "x-gzip, deflate".parse::<AcceptEncoding>().unwrap();
+        assert_eq!(accept.negotiate(&["identity", "GZIP"]), Some("GZIP"));
+        assert_eq!(accept.negotiate(&["br", "identity"]), Some("identity"));
+
+        let accept = "br;q=0.5, *;q=0.7".parse::<AcceptEncoding>().unwrap();
+        assert_eq!(accept.negotiate(&["br", "zstd"]), Some("zstd"));
+        assert_eq!(accept.negotiate(&["br", "identity"]), Some("identity"));
+
+        let accept = "*;q=0".parse::<AcceptEncoding>().unwrap();
+        assert_eq!(accept.negotiate(&["gzip", "identity"]), None);
+
+        // an empty header only accepts identity, a missing one accepts everything
+        let accept = "".parse::<AcceptEncoding>().unwrap();
+        assert_eq!(accept.negotiate(&["gzip", "identity"]), Some("identity"));
+        assert_eq!(accept.negotiate(&["gzip"]), None);
+        let accept = AcceptEncoding::any();
+        assert_eq!(accept.negotiate(&["gzip", "identity"]), Some("gzip"));
+    }
+}
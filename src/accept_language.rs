@@ -0,0 +1,197 @@
+use crate::{error::*, AcceptLanguage, LanguageRange, QValue, QualityValue};
+use snafu::ensure;
+use std::{cmp::Reverse, fmt, str::FromStr};
+
+impl AcceptLanguage {
+    /// Return the available language tags acceptable to the client, the preferred
+    /// first (RFC 4647 §3.3.1 basic filtering). Each tag is weighted by the longest range
+    /// matching it and tags weighted 0 are left out. Without any range, every tag is
+    /// acceptable.
+    pub fn filter<'a>(&self, available: &[&'a str]) -> Vec<&'a str> {
+        if self.items.is_empty() {
+            return available.to_vec();
+        }
+
+        let mut matched: Vec<_> = available
+            .iter()
+            .filter_map(|tag| Some((*tag, self.quality(tag)?)))
+            .filter(|(_, quality)| !quality.is_zero())
+            .collect();
+
+        matched.sort_by_key(|(_, quality)| Reverse(*quality));
+        matched.into_iter().map(|(tag, _)| tag).collect()
+    }
+
+    /// Find the best available language tag (RFC 4647 §3.4 lookup). Each range is
+    /// tried in order of preference, and progressively truncated (`zh-Hant-TW`,
+    /// `zh-Hant`, `zh`) until it equals an available tag that isn't excluded by a range
+    /// weighted 0. `default` is returned when no range leads to one.
+    pub fn lookup<'a>(&self, available: &[&'a str], default: &'a str) -> &'a str {
+        self.items
+            .iter()
+            .filter(|range| !range.quality().is_zero() && range.value.as_str() != "*")
+            .find_map(|range| {
+                let mut candidate = Some(range.value.as_str());
+                while let Some(prefix) = candidate {
+                    let tag = available.iter().find(|t| t.eq_ignore_ascii_case(prefix));
+                    if let Some(tag) = tag.filter(|tag| !self.is_excluded(tag)) {
+                        return Some(*tag);
+                    }
+                    candidate = truncate(prefix);
+                }
+                None
+            })
+            .unwrap_or(default)
+    }
+
+    /// The weight of the longest range matching `tag`, if any.
+    fn quality(&self, tag: &str) -> Option<QValue> {
+        self.items
+            .iter()
+            .filter(|range| range.value.matches(tag))
+            .max_by_key(|range| range.value.specificity())
+            .map(QualityValue::quality)
+    }
+
+    /// Whether the longest range matching `tag` is weighted 0.
+    fn is_excluded(&self, tag: &str) -> bool {
+        self.quality(tag).is_some_and(QValue::is_zero)
+    }
+}
+
+impl LanguageRange {
+    /// The language range as a string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Check whether this range matches the language tag, i.e. equals it or is a prefix
+    /// of it followed by `-`, ignoring case. `*` matches every tag.
+    pub fn matches(&self, tag: &str) -> bool {
+        let range = self.as_str();
+        if range == "*" {
+            return true;
+        }
+
+        tag.get(..range.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(range))
+            && matches!(tag.as_bytes().get(range.len()), None | Some(b'-'))
+    }
+
+    /// How specific the range is: the number of subtags, 0 for `*`.
+    fn specificity(&self) -> usize {
+        match self.as_str() {
+            "*" => 0,
+            range => range.split('-').count(),
+        }
+    }
+}
+
+impl FromStr for LanguageRange {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ensure!(is_language_range(s), LanguageRangeSnafu { value: s });
+
+        Ok(Self(s.to_owned()))
+    }
+}
+
+impl fmt::Display for LanguageRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// `language-range = (1*8ALPHA *("-" 1*8alphanum)) / "*"` (RFC 4647 §2.1).
+fn is_language_range(s: &str) -> bool {
+    if s == "*" {
+        return true;
+    }
+
+    s.split('-').enumerate().all(|(i, subtag)| {
+        (1..=8).contains(&subtag.len())
+            && subtag.bytes().all(|c| match i {
+                0 => c.is_ascii_alphabetic(),
+                _ => c.is_ascii_alphanumeric(),
+            })
+    })
+}
+
+/// Remove the last subtag of a range, along with a singleton (like `x`) left at the
+/// end. `None` once there is nothing left to remove.
+fn truncate(range: &str) -> Option<&str> {
+    let mut range = &range[..range.rfind('-')?];
+    if let Some(i) = range.rfind('-') {
+        if range.len() - i == 2 {
+            range = &range[..i];
+        }
+    }
+
+    Some(range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accept_language_should_be_parsed_and_sorted() {
+        let accept = "da, en-GB;q=0.8, *;q=0.1, en;q=0.7, zh-Hant-TW"
+            .parse::<AcceptLanguage>()
+            .unwrap();
+
+        assert_eq!(
+            accept.to_string(),
+            "da, zh-Hant-TW, en-GB;q=0.8, en;q=0.7, *;q=0.1"
+        );
+        assert_eq!(
+            "en-".parse::<AcceptLanguage>().unwrap_err().to_string(),
+            "Invalid language range: en-"
+        );
+        assert!("en_US".parse::<AcceptLanguage>().is_err());
+        assert!("123".parse::<AcceptLanguage>().is_err());
+        assert!("en;q=1.1".parse::<AcceptLanguage>().is_err());
+    }
+
+    #[test]
+    fn languages_should_be_filtered() {
+        let accept = "de-DE;q=0.5, en, de;q=0.8, fr;q=0"
+            .parse::<AcceptLanguage>()
+            .unwrap();
+
+        let available = ["fr-FR", "de-AT", "de-DE", "en-us", "EN", "english"];
+        assert_eq!(
+            accept.filter(&available),
+            vec!["en-us", "EN", "de-AT", "de-DE"]
+        );
+
+        let accept = AcceptLanguage::default();
+        assert_eq!(accept.filter(&available), available.to_vec());
+    }
+
+    #[test]
+    fn language_should_be_looked_up() {
+        let accept = "zh-Hant-TW, en;q=0.5".parse::<AcceptLanguage>().unwrap();
+
+        assert_eq!(accept.lookup(&["en", "zh-Hant", "zh"], "fr"), "zh-Hant");
+        assert_eq!(accept.lookup(&["en", "ZH"], "fr"), "ZH");
+        assert_eq!(accept.lookup(&["en-US", "en"], "fr"), "en");
+        assert_eq!(accept.lookup(&["en-US", "de"], "fr"), "fr");
+
+        let accept = "zh-Hant-CN-x-private1-private2, *"
+            .parse::<AcceptLanguage>()
+            .unwrap();
+        assert_eq!(
+            accept.lookup(&["zh-Hant-CN-x", "zh-Hant-CN", "de"], "fr"),
+            "zh-Hant-CN"
+        );
+        assert_eq!(accept.lookup(&["de"], "fr"), "fr");
+
+        // truncating doesn't reach an excluded language
+        let accept = "fr-CA, fr;q=0, en;q=0.5".parse::<AcceptLanguage>().unwrap();
+        assert_eq!(accept.lookup(&["fr", "en"], "de"), "en");
+        assert_eq!(accept.lookup(&["fr"], "de"), "de");
+        assert_eq!(accept.lookup(&["fr-CA", "en"], "de"), "fr-CA");
+    }
+}
@@ -0,0 +1,226 @@
+use crate::{
+    error::*, parse::varies_on_accept, Accept, AcceptHeaders, MalformedAccept, NegotiateGuard,
+    NegotiatedResponder, Offer, Renderers,
+};
+use actix_web::{
+    body::BoxBody,
+    dev::Payload,
+    guard::{Guard, GuardContext},
+    http::{
+        header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_TYPE, VARY},
+        StatusCode,
+    },
+    FromRequest, HttpRequest, HttpResponse, Responder, ResponseError,
+};
+use mime::Mime;
+use std::{
+    fmt,
+    future::{ready, Ready},
+};
+
+impl FromRequest for Accept {
+    type Error = Error;
+    type Future = Ready<Result<Self>>;
+
+    /// Extract the Accept header(s) of the request. A malformed header is handled as
+    /// the [`MalformedAccept`] app data says, rejected by default.
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(accept_of(req))
+    }
+}
+
+impl ResponseError for Error {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+impl ResponseError for NotAcceptable {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::NOT_ACCEPTABLE
+    }
+}
+
+impl AcceptHeaders for HeaderMap {
+    fn accept_values(&self) -> Vec<&[u8]> {
+        self.get_all(ACCEPT).map(HeaderValue::as_bytes).collect()
+    }
+}
+
+impl NegotiateGuard {
+    /// Pass the requests for which `chosen` is the best of `offers`. The offers are the
+    /// same for every route of a resource, each route choosing a different one.
+    pub fn new<O: Into<Offer>>(offers: impl IntoIterator<Item = O>, chosen: Mime) -> Self {
+        Self {
+            offers: offers.into_iter().map(Into::into).collect(),
+            chosen,
+        }
+    }
+}
+
+impl Guard for NegotiateGuard {
+    /// A malformed Accept header is handled as the [`MalformedAccept`] app data says:
+    /// when it is rejected, as by default, the request passes no guard.
+    fn check(&self, ctx: &GuardContext<'_>) -> bool {
+        let Ok(accept) = on_malformed(ctx.app_data()).accept(ctx.head().headers()) else {
+            return false;
+        };
+
+        accept
+            .negotiate(&self.offers)
+            .is_ok_and(|negotiated| *negotiated.mime() == self.chosen)
+    }
+}
+
+impl<T> NegotiatedResponder<T> {
+    /// Respond with `value`, rendered in one of the offers added with
+    /// [`NegotiatedResponder::offer`].
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            renderers: Renderers::default(),
+        }
+    }
+
+    /// Offer to render the value as `offer` with `render`. The first offer added wins a
+    /// tie.
+    pub fn offer<F, R>(mut self, offer: impl Into<Offer>, render: F) -> Self
+    where
+        F: FnOnce(T) -> R + 'static,
+        R: Responder,
+    {
+        self.renderers.push(
+            offer.into(),
+            Box::new(|value, req| render(value).respond_to(req).map_into_boxed_body()),
+        );
+        self
+    }
+}
+
+impl<T> Responder for NegotiatedResponder<T> {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        let accept = match accept_of(req) {
+            Ok(accept) => accept,
+            Err(e) => return e.error_response(),
+        };
+
+        let mut response = match self.renderers.choose(&accept) {
+            Ok((offer, render)) => {
+                let mut response = render(self.value, req);
+                let content_type = HeaderValue::from_str(offer.mime.as_ref())
+                    .expect("a media type is a valid header value");
+                response.headers_mut().insert(CONTENT_TYPE, content_type);
+                response
+            }
+            Err(e) => e.error_response(),
+        };
+
+        let headers = response.headers_mut();
+        if !varies_on_accept(headers.get_all(VARY).map(HeaderValue::as_bytes)) {
+            headers.append(VARY, HeaderValue::from_static("accept"));
+        }
+        response
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for NegotiatedResponder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NegotiatedResponder")
+            .field("value", &self.value)
+            .field("offers", &self.renderers)
+            .finish()
+    }
+}
+
+/// The `Accept` of a request, with a malformed header handled as configured.
+fn accept_of(req: &HttpRequest) -> Result<Accept> {
+    on_malformed(req.app_data()).accept(req.headers())
+}
+
+/// What to do with a malformed Accept header, given the app data.
+fn on_malformed(app_data: Option<&MalformedAccept>) -> MalformedAccept {
+    app_data.copied().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{body::to_bytes, test::TestRequest, web::Json};
+
+    fn request(accept: &str) -> TestRequest {
+        TestRequest::default().insert_header((ACCEPT, accept))
+    }
+
+    async fn respond(accept: &str) -> HttpResponse {
+        NegotiatedResponder::new(42)
+            .offer(mime::APPLICATION_JSON, |n| Json(vec![n]))
+            .offer(mime::TEXT_PLAIN, |n| format!("The answer is {n}"))
+            .respond_to(&request(accept).to_http_request())
+    }
+
+    #[tokio::test]
+    async fn accept_should_be_extracted() {
+        let accept = Accept::extract(&request("text/html, */*;q=0.1").to_http_request()).await;
+        assert_eq!(accept.unwrap().to_string(), "text/html, */*;q=0.1");
+
+        let err = Accept::extract(&request("text/html, foo").to_http_request())
+            .await
+            .unwrap_err();
+        assert_eq!(err.error_response().status(), StatusCode::BAD_REQUEST);
+
+        let req = request("text/html, foo")
+            .app_data(MalformedAccept::Skip)
+            .to_http_request();
+        assert_eq!(
+            Accept::extract(&req).await.unwrap().to_string(),
+            "text/html"
+        );
+    }
+
+    #[test]
+    fn guard_should_pass_the_negotiated_offer() {
+        let offers = [mime::APPLICATION_JSON, mime::TEXT_HTML];
+        let json = NegotiateGuard::new(offers.clone(), mime::APPLICATION_JSON);
+        let html = NegotiateGuard::new(offers, mime::TEXT_HTML);
+
+        let req = request("text/html, application/json;q=0.9").to_srv_request();
+        assert!(!json.check(&req.guard_ctx()));
+        assert!(html.check(&req.guard_ctx()));
+
+        let req = TestRequest::default().to_srv_request();
+        assert!(json.check(&req.guard_ctx()));
+        assert!(!html.check(&req.guard_ctx()));
+
+        let req = request("text/html, foo").to_srv_request();
+        assert!(!json.check(&req.guard_ctx()));
+        assert!(!html.check(&req.guard_ctx()));
+        let req = request("text/html, foo")
+            .app_data(MalformedAccept::Skip)
+            .to_srv_request();
+        assert!(html.check(&req.guard_ctx()));
+    }
+
+    #[tokio::test]
+    async fn response_should_be_negotiated() {
+        let response = respond("text/plain, application/json;q=0.5").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers().get(CONTENT_TYPE).unwrap(), "text/plain");
+        assert_eq!(response.headers().get(VARY).unwrap(), "accept");
+        let body = to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body, "The answer is 42");
+
+        let response = respond("*/*").await;
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        let body = to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body, "[42]");
+
+        let response = respond("image/png").await;
+        assert_eq!(response.status(), StatusCode::NOT_ACCEPTABLE);
+        assert_eq!(response.headers().get(VARY).unwrap(), "accept");
+    }
+}
@@ -0,0 +1,215 @@
+//! The axum integration, for axum 0.6 (`axum`) and 0.7 (`axum07`).
+
+use crate::{Accept, Negotiated, Renderers};
+use std::fmt;
+
+impl<T, R> Negotiated<T, R> {
+    /// Respond with `value`, rendered in one of the offers added with
+    /// [`Negotiated::offer`].
+    pub fn new(accept: Accept, value: T) -> Self {
+        Self {
+            accept,
+            value,
+            renderers: Renderers::default(),
+        }
+    }
+}
+
+impl<T: fmt::Debug, R> fmt::Debug for Negotiated<T, R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Negotiated")
+            .field("accept", &self.accept)
+            .field("value", &self.value)
+            .field("offers", &self.renderers)
+            .finish()
+    }
+}
+
+macro_rules! impl_axum {
+    ($axum_core:ident, $http:ident, $module:ident, $tests:ident, $read_body:path) => {
+        mod $module {
+            use crate::{
+                error::*, parse::varies_on_accept, Accept, MalformedAccept, Negotiated, Offer,
+            };
+            use $axum_core::{
+                extract::FromRequestParts,
+                response::{IntoResponse, Response},
+            };
+            use $http::{
+                header::{CONTENT_TYPE, VARY},
+                request::Parts,
+                HeaderValue, StatusCode,
+            };
+
+            #[async_trait::async_trait]
+            impl<S: Sync> FromRequestParts<S> for Accept {
+                type Rejection = Error;
+
+                /// Extract the Accept header(s) of the request. A malformed header is
+                /// handled as the [`MalformedAccept`] request extension says, rejected by
+                /// default.
+                async fn from_request_parts(
+                    parts: &mut Parts,
+                    _state: &S,
+                ) -> Result<Self, Self::Rejection> {
+                    parts
+                        .extensions
+                        .get::<MalformedAccept>()
+                        .copied()
+                        .unwrap_or_default()
+                        .accept(&parts.headers)
+                }
+            }
+
+            impl IntoResponse for Error {
+                fn into_response(self) -> Response {
+                    (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+                }
+            }
+
+            impl IntoResponse for NotAcceptable {
+                fn into_response(self) -> Response {
+                    (StatusCode::NOT_ACCEPTABLE, self.to_string()).into_response()
+                }
+            }
+
+            impl<T> Negotiated<T, Response> {
+                /// Offer to render the value as `offer` with `render`. The first offer
+                /// added wins a tie.
+                pub fn offer<F, R>(mut self, offer: impl Into<Offer>, render: F) -> Self
+                where
+                    F: FnOnce(T) -> R + Send + 'static,
+                    R: IntoResponse,
+                {
+                    self.renderers.push(
+                        offer.into(),
+                        Box::new(|value| render(value).into_response()),
+                    );
+                    self
+                }
+            }
+
+            impl<T> IntoResponse for Negotiated<T, Response> {
+                fn into_response(self) -> Response {
+                    let mut response = match self.renderers.choose(&self.accept) {
+                        Ok((offer, render)) => {
+                            let mut response = render(self.value);
+                            let content_type = HeaderValue::from_str(offer.mime.as_ref())
+                                .expect("a media type is a valid header value");
+                            response.headers_mut().insert(CONTENT_TYPE, content_type);
+                            response
+                        }
+                        Err(e) => e.into_response(),
+                    };
+
+                    let headers = response.headers_mut();
+                    if !varies_on_accept(headers.get_all(VARY).iter().map(HeaderValue::as_bytes)) {
+                        headers.append(VARY, HeaderValue::from_static("accept"));
+                    }
+                    response
+                }
+            }
+        }
+
+        #[cfg(test)]
+        mod $tests {
+            use crate::{error::*, Accept, MalformedAccept, Negotiated};
+            use $axum_core::{
+                extract::FromRequestParts,
+                response::{IntoResponse, Response},
+            };
+            use $http::{
+                header::{ACCEPT, CONTENT_TYPE, VARY},
+                Request, StatusCode,
+            };
+
+            async fn extract(
+                accept: &str,
+                on_malformed: Option<MalformedAccept>,
+            ) -> Result<Accept> {
+                let (mut parts, _) = Request::builder()
+                    .header(ACCEPT, accept)
+                    .body(())
+                    .unwrap()
+                    .into_parts();
+                if let Some(on_malformed) = on_malformed {
+                    parts.extensions.insert(on_malformed);
+                }
+
+                Accept::from_request_parts(&mut parts, &()).await
+            }
+
+            async fn body(response: Response) -> String {
+                $read_body(response.into_body()).await
+            }
+
+            fn negotiated(accept: &str) -> Response {
+                Negotiated::<_, Response>::new(accept.parse().unwrap(), 42)
+                    .offer(mime::APPLICATION_JSON, |n| format!(r#"{{"answer":{n}}}"#))
+                    .offer(mime::TEXT_PLAIN, |n| format!("The answer is {n}"))
+                    .into_response()
+            }
+
+            #[tokio::test]
+            async fn accept_should_be_extracted() {
+                let accept = extract("text/html, */*;q=0.1", None).await.unwrap();
+                assert_eq!(accept.to_string(), "text/html, */*;q=0.1");
+
+                let err = extract("text/html, foo", None).await.unwrap_err();
+                assert_eq!(err.into_response().status(), StatusCode::BAD_REQUEST);
+
+                let accept = extract("text/html, foo", Some(MalformedAccept::Skip)).await;
+                assert_eq!(accept.unwrap().to_string(), "text/html");
+                let accept = extract("text/html, foo", Some(MalformedAccept::Ignore)).await;
+                assert_eq!(accept.unwrap(), Accept::any());
+            }
+
+            #[tokio::test]
+            async fn response_should_be_negotiated() {
+                let response = negotiated("text/plain, application/json;q=0.5");
+                assert_eq!(response.status(), StatusCode::OK);
+                assert_eq!(response.headers()[CONTENT_TYPE], "text/plain");
+                assert_eq!(response.headers()[VARY], "accept");
+                assert_eq!(body(response).await, "The answer is 42");
+
+                let response = negotiated("*/*");
+                assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+                assert_eq!(body(response).await, r#"{"answer":42}"#);
+
+                let response = negotiated("image/png");
+                assert_eq!(response.status(), StatusCode::NOT_ACCEPTABLE);
+                assert_eq!(response.headers()[VARY], "accept");
+
+                let response = Negotiated::<_, Response>::new(Accept::any(), 42)
+                    .offer(mime::TEXT_PLAIN, |n| ([(VARY, "Accept")], n.to_string()))
+                    .into_response();
+                let vary: Vec<_> = response.headers().get_all(VARY).iter().collect();
+                assert_eq!(vary, ["Accept"]);
+            }
+        }
+    };
+}
+
+#[cfg(feature = "axum")]
+impl_axum!(axum_core, http02, axum06, axum06_tests, super::read_body_04);
+#[cfg(feature = "axum07")]
+impl_axum!(axum_core04, http1, axum07, axum07_tests, super::read_body_1);
+
+#[cfg(all(test, feature = "axum"))]
+async fn read_body_04(mut body: axum_core::body::BoxBody) -> String {
+    use http_body::Body as _;
+
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        bytes.extend_from_slice(&chunk.unwrap());
+    }
+    String::from_utf8(bytes).unwrap()
+}
+
+#[cfg(all(test, feature = "axum07"))]
+async fn read_body_1(body: axum_core04::body::Body) -> String {
+    use http_body_util::BodyExt;
+
+    let bytes = body.collect().await.unwrap().to_bytes();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
@@ -0,0 +1,236 @@
+use crate::{
+    error::*,
+    parse::{is_token, quote, quote_mime_param},
+    Accept, AcceptBuilder, MediaType, QValue,
+};
+use snafu::{ensure, ResultExt};
+use std::iter;
+
+impl Accept {
+    /// Start building an `Accept`, e.g. for a client request.
+    pub fn builder() -> AcceptBuilder {
+        AcceptBuilder::default()
+    }
+}
+
+impl AcceptBuilder {
+    /// Add a media range, e.g. `text/html` or `mime::TEXT_HTML`. It may come with
+    /// parameters and a weight, like in a header: `text/html;level=1;q=0.5`.
+    pub fn media_type(mut self, range: impl AsRef<str>) -> Self {
+        if let Some(media_type) = self.record(range.as_ref().parse()) {
+            self.types.push(media_type);
+        }
+        self
+    }
+
+    /// Set the weight of the last media range, a thousandth from 0.0 to 1.0. Other values
+    /// fail like in a header, e.g. `0.1234` with [`Error::ParseWeight`].
+    pub fn q(mut self, q: f32) -> Self {
+        let result = match self.types.last() {
+            Some(_) => q.try_into(),
+            None => ParameterSnafu {
+                value: format!("q={q}"),
+            }
+            .fail(),
+        };
+
+        if let (Some(weight), Some(last)) = (self.record(result), self.types.last_mut()) {
+            last.weight = Some(weight);
+        }
+        self
+    }
+
+    /// Add a parameter to the last media range. The value is quoted when needed.
+    pub fn param(mut self, name: &str, value: &str) -> Self {
+        let result = match self.types.last() {
+            Some(last) => quote_mime_param(value).and_then(|value| {
+                let param = format!("{name}={value}");
+                ensure_param(name, &param)?;
+                let source = format!("{};{param}", last.mime);
+                source.parse().context(MediaTypeSnafu { value: source })
+            }),
+            None => ParameterSnafu {
+                value: format!("{name}={}", quote(value)),
+            }
+            .fail(),
+        };
+
+        if let (Some(mime), Some(last)) = (self.record(result), self.types.last_mut()) {
+            last.mime = mime;
+        }
+        self
+    }
+
+    /// Add media ranges in order of preference, weighted 1, 0.9, 0.8 and so on down to
+    /// 0.1, then 0.09 down to 0.01, and 0.009 down to 0.001 for the rest. Weights given
+    /// with the ranges are replaced.
+    pub fn prefer_in_order<I>(mut self, ranges: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+    {
+        let weights = iter::successors(Some(QValue::ONE), |q| {
+            let q = q.as_thousandths();
+            let step = match q {
+                101.. => 100,
+                11.. => 10,
+                _ => 1,
+            };
+            QValue::from_thousandths(q.saturating_sub(step).max(1))
+        });
+
+        for (range, weight) in ranges.into_iter().zip(weights) {
+            if let Some(media_type) = self.record(range.as_ref().parse::<MediaType>()) {
+                self.types.push(MediaType {
+                    weight: (weight != QValue::ONE).then_some(weight),
+                    ..media_type
+                });
+            }
+        }
+        self
+    }
+
+    /// Build the `Accept`, failing with the first invalid media range, parameter or
+    /// weight. Duplicate media ranges are merged like when parsing a header.
+    pub fn build(self) -> Result<Accept> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.types.into_iter().collect()),
+        }
+    }
+
+    /// Keep the first error, returning the value otherwise.
+    fn record<T>(&mut self, result: Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.error.get_or_insert(e);
+                None
+            }
+        }
+    }
+}
+
+/// Check the name of a media type parameter; `q` is reserved for the weight.
+fn ensure_param(name: &str, param: &str) -> Result<()> {
+    ensure!(
+        is_token(name) && !name.eq_ignore_ascii_case("q"),
+        ParameterSnafu { value: param }
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::QualityValue;
+
+    #[test]
+    fn accept_should_be_built() {
+        let accept = Accept::builder()
+            .media_type("text/html")
+            .param("level", "1")
+            .media_type(mime::APPLICATION_JSON)
+            .q(0.9)
+            .media_type("application/ld+json;q=0.5")
+            .param("profile", "https://a, https://b")
+            .media_type("*/*")
+            .q(0.1)
+            .media_type(mime::APPLICATION_JSON)
+            .q(0.4)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            accept.to_string(),
+            r#"text/html;level=1, application/json;q=0.9, application/ld+json;profile="https://a, https://b";q=0.5, */*;q=0.1"#
+        );
+        assert_eq!(accept, accept.to_string().parse().unwrap());
+        assert_eq!(Accept::builder().build().unwrap(), Accept::any());
+    }
+
+    #[test]
+    fn invalid_accept_should_not_be_built() {
+        let cases = [
+            (
+                Accept::builder().media_type("html"),
+                "Invalid media type: html",
+            ),
+            (
+                Accept::builder().media_type("*/html"),
+                "Invalid media range: */html (only */* may have a wildcard type)",
+            ),
+            (
+                Accept::builder().media_type("text/html").q(1.5),
+                "Weight should be 0.0-1.0. Got 1.5",
+            ),
+            (
+                Accept::builder().media_type("text/html").param("q", "1"),
+                "Invalid parameter: q=1",
+            ),
+            (
+                Accept::builder().media_type("text/html").param("a b", "1"),
+                r#"Invalid parameter: a b=1"#,
+            ),
+            (
+                Accept::builder()
+                    .media_type("application/json")
+                    .param("profile", r#"say "hi""#),
+                r#"Unsupported parameter value (a media type can't hold a '"'): say "hi""#,
+            ),
+            (
+                Accept::builder().media_type("text/html").q(0.1234),
+                "Invalid weight: 0.1234",
+            ),
+            (
+                Accept::builder().media_type("text/html").q(0.0004),
+                "Invalid weight: 0.0004",
+            ),
+            (Accept::builder().q(0.5), "Invalid parameter: q=0.5"),
+            (
+                Accept::builder()
+                    .media_type("foo")
+                    .media_type("text/html")
+                    .q(2.0),
+                "Invalid media type: foo",
+            ),
+        ];
+
+        for (builder, expected) in cases {
+            assert_eq!(builder.build().unwrap_err().to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn preferred_ranges_should_get_descending_weights() {
+        let accept = Accept::builder()
+            .prefer_in_order(["application/json", "text/html", "text/*"])
+            .media_type("*/*")
+            .q(0.01)
+            .build()
+            .unwrap();
+        assert_eq!(
+            accept.to_string(),
+            "application/json, text/html;q=0.9, text/*;q=0.8, */*;q=0.01"
+        );
+
+        let ranges: Vec<_> = (0..30).map(|i| format!("text/x-{i}")).collect();
+        let accept = Accept::builder().prefer_in_order(&ranges).build().unwrap();
+        let weights: Vec<_> = accept
+            .types
+            .iter()
+            .map(|t| t.quality().as_thousandths())
+            .collect();
+        assert_eq!(
+            weights[..11],
+            [1000, 900, 800, 700, 600, 500, 400, 300, 200, 100, 90]
+        );
+        assert_eq!(weights[18..21], [10, 9, 8]);
+        assert_eq!(weights[27..], [1, 1, 1]);
+        assert!(accept
+            .types
+            .iter()
+            .zip(&ranges)
+            .all(|(t, r)| t.mime == r.as_str()));
+    }
+}
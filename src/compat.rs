@@ -0,0 +1,130 @@
+//! Conversions from the types of the `http` crate, for each supported version.
+
+macro_rules! impl_http {
+    ($http:ident, $tests:ident) => {
+        impl crate::AcceptHeaders for $http::HeaderMap {
+            fn accept_values(&self) -> Vec<&[u8]> {
+                self.get_all($http::header::ACCEPT)
+                    .iter()
+                    .map($http::HeaderValue::as_bytes)
+                    .collect()
+            }
+        }
+
+        impl TryFrom<&$http::HeaderValue> for crate::Accept {
+            type Error = crate::error::Error;
+
+            fn try_from(value: &$http::HeaderValue) -> Result<Self, Self::Error> {
+                Self::from_header(Some(value))
+            }
+        }
+
+        impl TryFrom<&$http::HeaderMap> for crate::Accept {
+            type Error = crate::error::Error;
+
+            fn try_from(headers: &$http::HeaderMap) -> Result<Self, Self::Error> {
+                Self::from_headers(headers, crate::NonUtf8::Reject)
+            }
+        }
+
+        impl TryFrom<&crate::Accept> for $http::HeaderValue {
+            type Error = $http::header::InvalidHeaderValue;
+
+            fn try_from(accept: &crate::Accept) -> Result<Self, Self::Error> {
+                Self::from_bytes(accept.to_string().as_bytes())
+            }
+        }
+
+        impl From<crate::error::NotAcceptable> for $http::StatusCode {
+            fn from(_: crate::error::NotAcceptable) -> Self {
+                $http::StatusCode::NOT_ACCEPTABLE
+            }
+        }
+
+        #[cfg(test)]
+        mod $tests {
+            use crate::{error::Error, Accept, NonUtf8};
+            use $http::{header::ACCEPT, HeaderMap, HeaderValue, StatusCode};
+
+            #[test]
+            fn accept_should_merge_multiple_header_lines() {
+                let mut headers = HeaderMap::new();
+                assert_eq!(
+                    Accept::from_headers(&headers, NonUtf8::Reject).unwrap(),
+                    Accept::any()
+                );
+
+                headers.append(
+                    ACCEPT,
+                    HeaderValue::from_static("text/plain;q=0.5, */*;q=0.1"),
+                );
+                headers.append(ACCEPT, HeaderValue::from_static(""));
+                headers.append(ACCEPT, HeaderValue::from_static("application/json"));
+
+                let accept = Accept::try_from(&headers).unwrap();
+                assert_eq!(
+                    accept,
+                    "text/plain;q=0.5, */*;q=0.1, application/json"
+                        .parse()
+                        .unwrap()
+                );
+                assert_eq!(
+                    accept.to_string(),
+                    "application/json, text/plain;q=0.5, */*;q=0.1"
+                );
+
+                headers.append(ACCEPT, HeaderValue::from_bytes(b"text/\xff").unwrap());
+                headers.append(
+                    ACCEPT,
+                    HeaderValue::from_static("text/html;q=abc, text/csv"),
+                );
+                assert!(matches!(
+                    Accept::from_headers(&headers, NonUtf8::Reject),
+                    Err(Error::HeaderValue { .. })
+                ));
+                assert!(matches!(
+                    Accept::from_headers(&headers, NonUtf8::Skip),
+                    Err(Error::ParseWeight { .. })
+                ));
+
+                let (accept, errors) = Accept::from_headers_lenient(&headers);
+                assert_eq!(
+                    accept.types,
+                    vec![
+                        mime::APPLICATION_JSON,
+                        mime::TEXT_CSV,
+                        mime::TEXT_PLAIN,
+                        mime::STAR_STAR
+                    ]
+                );
+                assert_eq!(errors.len(), 2);
+                assert!(matches!(errors[0], Error::HeaderValue { .. }));
+                assert!(matches!(errors[1], Error::ParseWeight { .. }));
+
+                headers.remove(ACCEPT);
+                headers.append(ACCEPT, HeaderValue::from_bytes(b"text/\xff").unwrap());
+                headers.append(ACCEPT, HeaderValue::from_static("text/csv"));
+                let accept = Accept::from_headers(&headers, NonUtf8::Skip).unwrap();
+                assert_eq!(accept.types, vec![mime::TEXT_CSV]);
+            }
+
+            #[test]
+            fn header_value_should_be_converted() {
+                let value = HeaderValue::from_static("text/html, text/*;q=0.1");
+                let accept = Accept::try_from(&value).unwrap();
+                assert_eq!(accept, Accept::from_header(Some(&value)).unwrap());
+                assert_eq!(accept.to_string(), "text/html, text/*;q=0.1");
+
+                assert_eq!(HeaderValue::try_from(&accept).unwrap(), value);
+
+                let err = accept.negotiate(&[mime::IMAGE_PNG]).unwrap_err();
+                assert_eq!(StatusCode::from(err), StatusCode::NOT_ACCEPTABLE);
+            }
+        }
+    };
+}
+
+#[cfg(feature = "http02")]
+impl_http!(http02, http02_tests);
+#[cfg(feature = "http1")]
+impl_http!(http1, http1_tests);
@@ -1,3 +1,6 @@
+use crate::MediaType;
+use itertools::Itertools;
+use mime::Mime;
 use snafu::Snafu;
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -10,11 +13,51 @@ pub enum Error {
         value: String,
         source: mime::FromStrError,
     },
+    #[snafu(display("Invalid media range: {value} (only */* may have a wildcard type)"))]
+    WildcardType { value: String },
+    #[snafu(display("Invalid token: {value}"))]
+    Token { value: String },
+    #[snafu(display("Invalid language range: {value}"))]
+    LanguageRange { value: String },
+    #[snafu(display("Invalid parameter: {value}"))]
+    Parameter { value: String },
+    #[snafu(display("Unsupported parameter value (a media type can't hold a '\"'): {value}"))]
+    UnsupportedParameter { value: String },
     #[snafu(display("Invalid weight: {value}"))]
-    ParseWeight {
+    ParseWeight { value: String },
+    #[snafu(display("Header value is not valid UTF-8: {value:?}"))]
+    HeaderValue {
         value: String,
-        source: std::num::ParseFloatError,
+        source: std::str::Utf8Error,
     },
     #[snafu(display("Weight should be 0.0-1.0. Got {value}"))]
-    WeightRange { value: f32 },
+    WeightRange { value: String },
+}
+
+impl From<std::convert::Infallible> for Error {
+    fn from(e: std::convert::Infallible) -> Self {
+        match e {}
+    }
+}
+
+/// None of the offered media types is acceptable to the client (406 Not Acceptable).
+#[derive(Debug, Clone, PartialEq, Snafu)]
+#[snafu(display(
+    "None of {} is acceptable to {}",
+    offers.iter().join(", "),
+    ranges.iter().join(", ")
+))]
+pub struct NotAcceptable {
+    /// Everything the server offered.
+    pub offers: Vec<Mime>,
+    /// The media ranges the client accepts, i.e. those with a non-zero weight.
+    pub ranges: Vec<MediaType>,
+}
+
+impl NotAcceptable {
+    /// The HTTP status code for this error, 406. With the `http02` or `http1` feature,
+    /// `NotAcceptable` also converts into the `StatusCode` of that http version.
+    pub fn status_code(&self) -> u16 {
+        406
+    }
 }
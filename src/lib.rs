@@ -2,7 +2,7 @@
     clippy::all,
     clippy::dbg_macro,
     clippy::todo,
-    clippy::empty_enum,
+    clippy::empty_enums,
     clippy::enum_glob_use,
     clippy::mem_forget,
     clippy::unused_self,
@@ -11,9 +11,7 @@
     clippy::needless_borrow,
     clippy::match_wildcard_for_single_variants,
     clippy::if_let_mutex,
-    clippy::mismatched_target_os,
     clippy::await_holding_lock,
-    clippy::match_on_vec_items,
     clippy::imprecise_flops,
     clippy::suboptimal_flops,
     clippy::lossy_float_literal,
@@ -39,21 +37,284 @@
 #![cfg_attr(docsrs, feature(doc_auto_cfg, doc_cfg))]
 #![cfg_attr(test, allow(clippy::float_cmp))]
 
+#[cfg(all(feature = "tower", not(any(feature = "http02", feature = "http1"))))]
+compile_error!("the `tower` feature needs the `http02` or `http1` feature");
+
 mod accept;
+mod accept_charset;
+mod accept_encoding;
+mod accept_language;
+#[cfg(feature = "actix")]
+mod actix;
+#[cfg(any(feature = "axum", feature = "axum07"))]
+mod axum;
+mod builder;
+#[cfg(any(feature = "http02", feature = "http1"))]
+mod compat;
 mod media_type;
+mod negotiation;
+mod offer;
+mod parse;
+mod quality;
+mod qvalue;
+#[cfg(feature = "serde")]
+mod serialization;
+#[cfg(feature = "tower")]
+mod tower;
+#[cfg(any(feature = "headers", feature = "headers04"))]
+mod typed_header;
 
 pub mod error;
 
+#[cfg(feature = "serde")]
+pub use crate::serialization::{Expand, ExpandedItem, ExpandedMediaType};
+#[cfg(feature = "tower")]
+pub use crate::tower::NegotiateFuture;
+
 use mime::Mime;
+use std::{fmt, str::FromStr};
 
-#[derive(Debug, Clone, PartialEq)]
+/// An Accept header: its media ranges, the most preferred first. Two `Accept`s are equal
+/// when they list the same ranges in the same order, wherever the ranges came in the
+/// headers they were parsed from.
+#[derive(Debug, Clone, Default)]
 pub struct Accept {
-    pub wildcard: Option<MediaType>,
     pub types: Vec<MediaType>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// Builds an [`Accept`] header, e.g. for a client request. See [`Accept::builder`].
+#[derive(Debug, Default)]
+pub struct AcceptBuilder {
+    types: Vec<MediaType>,
+    error: Option<error::Error>,
+}
+
+/// A media range of an Accept header. Media ranges are ordered by precedence: weight,
+/// then specificity, then position in the header (earlier is greater).
+#[derive(Debug, Clone)]
 pub struct MediaType {
+    /// The media range with its parameters. Like in any `Mime`, a quoted parameter value
+    /// is kept in its escaped form: `get_param` gives `a\\b` for `x="a\\b"`, while an
+    /// unneeded escape like the one in `x="a\;b"` is dropped.
+    pub mime: Mime,
+    pub weight: Option<QValue>,
+    pub extensions: Vec<(String, Option<String>)>,
+    /// Where the media range came in the header it was parsed from, 0 for the first.
+    pub position: usize,
+}
+
+/// A q-value (RFC 9110 §12.4.2): a weight from 0 to 1 with at most three decimals,
+/// stored exactly in thousandths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct QValue(u16);
+
+/// A q-weighted header list such as Accept-Language, sorted by weight (RFC 9110
+/// §12.4.2). Elements with the same weight keep their order in the header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityList<T> {
+    pub items: Vec<T>,
+}
+
+/// A list element made of a plain value and an optional weight, e.g. `gzip;q=0.8`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityItem<T> {
+    pub value: T,
+    pub weight: Option<QValue>,
+}
+
+/// An element of a [`QualityList`]. Implemented by [`MediaType`] and [`QualityItem`];
+/// implement it to parse a weighted header of your own.
+pub trait QualityValue: FromStr<Err = error::Error> + fmt::Display {
+    /// The weight of the element, `None` when it has no `q` parameter.
+    fn weight(&self) -> Option<QValue>;
+
+    /// Effective weight of the element. A missing `q` means 1.
+    fn quality(&self) -> QValue {
+        self.weight().unwrap_or(QValue::ONE)
+    }
+}
+
+/// A parsed Accept-Charset header (RFC 9110 §12.5.2).
+pub type AcceptCharset = QualityList<QualityItem<Charset>>;
+
+/// A charset such as `utf-8`, or `*`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Charset(String);
+
+/// A parsed Accept-Encoding header (RFC 9110 §12.5.3).
+pub type AcceptEncoding = QualityList<QualityItem<Coding>>;
+
+/// A content coding such as `gzip` or `identity`, or `*`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coding(String);
+
+/// A parsed Accept-Language header (RFC 9110 §12.5.4).
+pub type AcceptLanguage = QualityList<QualityItem<LanguageRange>>;
+
+/// A BCP 47 language range such as `en`, `zh-Hant-TW` or `*` (RFC 4647 §2.1).
+#[derive(Debug, Clone, PartialEq)]
+pub struct LanguageRange(String);
+
+/// The outcome of a successful [`Accept::negotiate`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Negotiation<'a, O> {
+    /// The chosen offer.
+    pub offer: &'a O,
+    /// The client's media range that matched the offer.
+    pub range: &'a MediaType,
+    /// The effective quality: the range's q-value times the offer's server quality.
+    pub quality: QValue,
+    /// How the offer was matched.
+    pub kind: MatchKind,
+}
+
+/// How an offer matched the client's media ranges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum MatchKind {
+    /// A concrete `type/subtype` range, possibly with parameters.
+    Exact,
+    /// A `type/*` range.
+    SubtypeWildcard,
+    /// The `*/*` range.
+    FullWildcard,
+    /// The client didn't state any media range, so everything is acceptable.
+    Default,
+}
+
+/// A representation the server is able to produce, with the server's own quality
+/// for it (like Apache's `qs`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Offer {
     pub mime: Mime,
-    pub weight: Option<f32>,
+    pub quality: QValue,
+}
+
+/// Selects the expanded serde form of a type instead of its header string, e.g.
+/// `{"mime": "text/html", "q": 0.5, "params": {"level": "1"}}` for a [`MediaType`].
+/// Implemented for [`Accept`], [`MediaType`], [`Offer`], [`QValue`] (as a number),
+/// [`QualityList`] and [`QualityItem`]. Like the header string, it leaves out [`MediaType::position`].
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Structured<T>(pub T);
+
+/// A map of header fields that [`Accept::from_headers`] can read the Accept field lines
+/// from. Implemented for the `HeaderMap` of http 0.2 and 1.x with the `http02` and
+/// `http1` features.
+pub trait AcceptHeaders {
+    /// The values of every Accept field line, in order.
+    fn accept_values(&self) -> Vec<&[u8]>;
+}
+
+/// What [`Accept::from_headers`] does with an Accept field line that isn't valid UTF-8.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum NonUtf8 {
+    /// Fail with [`Error::HeaderValue`](error::Error::HeaderValue).
+    #[default]
+    Reject,
+    /// Skip the field line and keep the others.
+    Skip,
+}
+
+/// Anything that can be offered to [`Accept::negotiate`].
+pub trait AsOffer {
+    /// The media type of the offered representation.
+    fn mime(&self) -> &Mime;
+
+    /// The server quality of the representation.
+    fn quality(&self) -> QValue {
+        QValue::ONE
+    }
+}
+
+/// What the axum and actix-web extractors for [`Accept`] do with a malformed Accept
+/// header. Add it to the request extensions (e.g. with an `axum::Extension` layer) or to
+/// the actix-web app data to override the default.
+#[cfg(any(feature = "axum", feature = "axum07", feature = "actix"))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum MalformedAccept {
+    /// Reject the request with 400 Bad Request.
+    #[default]
+    Reject,
+    /// Skip the malformed media ranges and keep the others.
+    Skip,
+    /// Ignore the whole header, accepting any media type.
+    Ignore,
+}
+
+/// The offers of a negotiated response, each with its renderer, in the order they were
+/// added.
+#[cfg(any(feature = "axum", feature = "axum07", feature = "actix"))]
+pub(crate) struct Renderers<R> {
+    offers: Vec<Offer>,
+    renderers: Vec<R>,
+}
+
+/// An axum response rendered in whichever offered media type [`Accept::negotiate`]
+/// picks, with a matching `Content-Type` and `Vary: Accept`. Responds with 406 Not
+/// Acceptable when none of them is acceptable. `R` is the `Response` of axum 0.6 with the
+/// `axum` feature, or of axum 0.7 with the `axum07` feature; with both, it has to be
+/// named, e.g. `Negotiated::<_, axum::response::Response>::new`.
+#[cfg(any(feature = "axum", feature = "axum07"))]
+pub struct Negotiated<T, R = AxumResponse> {
+    accept: Accept,
+    value: T,
+    renderers: Renderers<Box<dyn FnOnce(T) -> R + Send>>,
+}
+
+#[cfg(feature = "axum")]
+type AxumResponse = axum_core::response::Response;
+#[cfg(all(feature = "axum07", not(feature = "axum")))]
+type AxumResponse = axum_core04::response::Response;
+
+/// A [`tower_layer::Layer`] negotiating every request against a fixed list of offers.
+/// See [`Negotiate`].
+#[cfg(feature = "tower")]
+#[derive(Debug, Clone)]
+pub struct NegotiateLayer {
+    offers: std::sync::Arc<[Offer]>,
+}
+
+/// Middleware that negotiates the request's Accept header against a list of offers.
+/// The chosen [`Mime`] is put into the request extensions for the inner service; when
+/// nothing is acceptable it responds with 406 Not Acceptable instead of calling it.
+/// Every response gets `Vary: Accept`. A `Service` for the requests of http 1.x and 0.2,
+/// with the `http1` and `http02` features.
+#[cfg(feature = "tower")]
+#[derive(Debug, Clone)]
+pub struct Negotiate<S> {
+    inner: S,
+    offers: std::sync::Arc<[Offer]>,
+}
+
+/// An actix-web guard passing the requests for which [`Accept::negotiate`] picks a given
+/// offer, so that each representation can be routed to its own handler.
+#[cfg(feature = "actix")]
+#[derive(Debug, Clone)]
+pub struct NegotiateGuard {
+    offers: Vec<Offer>,
+    chosen: Mime,
+}
+
+/// An actix-web responder rendering a value in whichever offered media type
+/// [`Accept::negotiate`] picks for the request, with a matching `Content-Type` and
+/// `Vary: Accept`. Responds with 406 Not Acceptable when none of them is acceptable.
+#[cfg(feature = "actix")]
+pub struct NegotiatedResponder<T> {
+    value: T,
+    renderers: Renderers<Box<dyn FnOnce(T, &actix_web::HttpRequest) -> actix_web::HttpResponse>>,
 }
@@ -1,39 +1,145 @@
 use mime::Mime;
-use snafu::{ensure, ResultExt};
+use snafu::{ensure, OptionExt, ResultExt};
 
-use crate::{error::*, MediaType};
-use std::{cmp::Ordering, fmt, str::FromStr};
+use crate::{
+    error::*,
+    parse::{parse_param, quote, quote_mime_param, split_quoted},
+    MediaType, QValue, QualityValue,
+};
+use itertools::Itertools;
+use std::{
+    cmp::Ordering,
+    fmt,
+    fmt::Write,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
 
 impl FromStr for MediaType {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.split(';');
-        let v = parts.next().unwrap().trim();
-        let mime = v.parse().context(MediaTypeSnafu { value: v })?;
-        let Some(v) = parts
-            .next()
-            .map(|s| s.trim())
-            .and_then(|s| s.strip_prefix("q=")) else {
-            return Ok(MediaType { mime, weight: None });
-            };
-
-        let weight: f32 = v.trim().parse().context(ParseWeightSnafu { value: v })?;
+        let mut parts = split_quoted(s, b';');
+        let mut source = parts.next().unwrap_or_default().trim().to_owned();
+        let mut weight = None;
+        let mut extensions = Vec::new();
+
+        // media type parameters come before `q`, accept extensions after it
+        for part in parts.filter(|part| !part.trim().is_empty()) {
+            let (name, value) = parse_param(part)?;
+            if weight.is_some() {
+                extensions.push((name.to_owned(), value));
+            } else if name.eq_ignore_ascii_case("q") {
+                let v = value.context(ParameterSnafu { value: part })?;
+                weight = Some(v.parse()?);
+            } else {
+                let value = value.context(ParameterSnafu { value: part })?;
+                let value = quote_mime_param(&value)?;
+                write!(source, ";{name}={value}").expect("write to string");
+            }
+        }
+
+        let mime: Mime = source.parse().context(MediaTypeSnafu { value: &source })?;
         ensure!(
-            (0.0..=1.0).contains(&weight),
-            WeightRangeSnafu { value: weight }
+            mime.type_() != mime::STAR || mime.subtype() == mime::STAR,
+            WildcardTypeSnafu { value: source }
         );
 
         Ok(MediaType {
             mime,
-            weight: Some(weight),
+            weight,
+            extensions,
+            position: 0,
+        })
+    }
+}
+
+impl MediaType {
+    /// Check whether this media range matches the given media type. `*/*` matches
+    /// everything and `type/*` matches every subtype of `type`. A range with parameters
+    /// only matches when every one of them is present and equal on `mime`.
+    pub fn matches(&self, mime: &Mime) -> bool {
+        let matched = match (self.mime.type_(), self.mime.subtype()) {
+            (mime::STAR, mime::STAR) => true,
+            (type_, mime::STAR) => type_ == mime.type_(),
+            _ => self.mime.essence_str() == mime.essence_str(),
+        };
+
+        matched && self.params_match(mime)
+    }
+
+    /// Whether `mime` is this very media range, parameters included in any order.
+    pub(crate) fn is_same_range(&self, mime: &Mime) -> bool {
+        self.mime.essence_str() == mime.essence_str()
+            && self.mime.params().count() == mime.params().count()
+            && self.params_match(mime)
+    }
+
+    /// Whether every parameter of this range is present and equal on `mime`.
+    fn params_match(&self, mime: &Mime) -> bool {
+        self.mime.params().all(|(name, value)| {
+            mime.get_param(name.as_str())
+                .is_some_and(|v| v == value.as_str())
         })
     }
+
+    /// Whether this is the `*/*` media range.
+    pub(crate) fn is_wildcard(&self) -> bool {
+        self.mime.type_() == mime::STAR
+    }
+
+    /// How specific the media range is: `*/*` < `type/*` < `type/subtype`, and within
+    /// each of those, more parameters are more specific.
+    pub(crate) fn specificity(&self) -> (u8, usize) {
+        let level = match (self.mime.type_(), self.mime.subtype()) {
+            (mime::STAR, _) => 0,
+            (_, mime::STAR) => 1,
+            _ => 2,
+        };
+
+        (level, self.mime.params().count())
+    }
+
+    /// Everything identifying the media range besides its precedence, with the
+    /// parameters sorted so that their order doesn't matter.
+    pub(crate) fn identity(
+        &self,
+    ) -> (
+        &str,
+        Vec<(&str, &str)>,
+        Option<QValue>,
+        &[(String, Option<String>)],
+    ) {
+        let params = self
+            .mime
+            .params()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .sorted()
+            .collect();
+
+        (
+            self.mime.essence_str(),
+            params,
+            self.weight,
+            &self.extensions,
+        )
+    }
+}
+
+impl QualityValue for MediaType {
+    fn weight(&self) -> Option<QValue> {
+        self.weight
+    }
 }
 
 impl From<Mime> for MediaType {
     fn from(mime: Mime) -> Self {
-        Self { mime, weight: None }
+        Self {
+            mime,
+            weight: None,
+            extensions: Vec::new(),
+            position: 0,
+        }
     }
 }
 
@@ -55,24 +161,38 @@ impl PartialEq<Mime> for &MediaType {
     }
 }
 
+impl PartialEq for MediaType {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MediaType {}
+
+impl Hash for MediaType {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.identity().hash(state);
+        self.position.hash(state);
+    }
+}
+
 impl PartialOrd for MediaType {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        let aweight = self.weight.unwrap_or(1.0);
-        let bweight = other.weight.unwrap_or(1.0);
-        match aweight.partial_cmp(&bweight) {
-            Some(Ordering::Equal) => match (self.mime.type_(), other.mime.type_()) {
-                (mime::STAR, mime::STAR) => match (self.mime.subtype(), other.mime.subtype()) {
-                    (mime::STAR, mime::STAR) => Some(Ordering::Equal),
-                    (mime::STAR, _) => Some(Ordering::Less),
-                    (_, mime::STAR) => Some(Ordering::Greater),
-                    (_, _) => None,
-                },
-                (mime::STAR, _) => Some(Ordering::Less),
-                (_, mime::STAR) => Some(Ordering::Greater),
-                (_, _) => None,
-            },
-            v => v,
-        }
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MediaType {
+    /// Order by weight first, then by specificity (see RFC 9110 §12.5.1), then by
+    /// position: the earlier of two otherwise equal ranges is the greater one. Ranges
+    /// that still tie are ordered by their mime, parameters and extensions, so the
+    /// order is total.
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.quality()
+            .cmp(&other.quality())
+            .then_with(|| self.specificity().cmp(&other.specificity()))
+            .then_with(|| other.position.cmp(&self.position))
+            .then_with(|| self.identity().cmp(&other.identity()))
     }
 }
 
@@ -80,8 +200,18 @@ impl fmt::Display for MediaType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.mime)?;
 
-        if let Some(weight) = self.weight {
-            write!(f, ";q={weight}")?;
+        match self.weight {
+            Some(weight) => write!(f, ";q={weight}")?,
+            // extensions are only recognized after the weight
+            None if !self.extensions.is_empty() => write!(f, ";q=1")?,
+            None => {}
+        }
+
+        for (name, value) in &self.extensions {
+            write!(f, ";{name}")?;
+            if let Some(value) = value {
+                write!(f, "={}", quote(value))?;
+            }
         }
 
         Ok(())
@@ -102,11 +232,49 @@ mod tests {
         assert_eq!(t1.mime.subtype(), mime::HTML);
         assert_eq!(t2.mime.type_(), mime::APPLICATION);
         assert_eq!(t2.mime.subtype(), mime::JSON);
-        assert_eq!(t1.weight, Some(0.5));
+        assert_eq!(t1.weight, "0.5".parse().ok());
         assert_eq!(t2.weight, None);
         assert_eq!(t3.mime.type_(), mime::STAR);
     }
 
+    #[test]
+    fn media_type_parameters_should_be_preserved() {
+        let t1: MediaType = "text/html;level=1;q=0.5".parse().unwrap();
+        let t2: MediaType = "application/json; charset=utf-8".parse().unwrap();
+        let t3: MediaType =
+            r#"application/ld+json;profile="https://a\;b, https://c";Q=0.8;ext;foo="x,y""#
+                .parse()
+                .unwrap();
+
+        assert_eq!(t1.mime.essence_str(), "text/html");
+        assert_eq!(t1.mime.get_param("level").unwrap(), "1");
+        assert_eq!(t1.weight, "0.5".parse().ok());
+        assert!(t1.extensions.is_empty());
+        assert_eq!(t2.mime.get_param(mime::CHARSET), Some(mime::UTF_8));
+        assert_eq!(t2.weight, None);
+        assert_eq!(
+            t3.mime.get_param("profile").unwrap(),
+            "https://a;b, https://c"
+        );
+        assert_eq!(t3.weight, "0.8".parse().ok());
+        let t4: MediaType = r#"text/plain;x="a\\b";y="c\d""#.parse().unwrap();
+        assert_eq!(t4.mime.get_param("x").unwrap(), r"a\\b");
+        assert_eq!(t4.mime.get_param("y").unwrap(), "cd");
+        assert_eq!(t4.to_string(), r#"text/plain;x="a\\b";y=cd"#);
+        assert!(t4.matches(&r#"text/plain;x="a\\b";y=cd"#.parse().unwrap()));
+        assert_eq!(
+            t3.extensions,
+            vec![
+                ("ext".to_owned(), None),
+                ("foo".to_owned(), Some("x,y".to_owned()))
+            ]
+        );
+        assert_eq!(
+            t3.to_string(),
+            r#"application/ld+json;profile="https://a;b, https://c";q=0.8;ext;foo="x,y""#
+        );
+    }
+
     #[test]
     fn invalid_media_type_should_be_rejected() {
         let t1 = "text/html; q=-0.5".parse::<MediaType>();
@@ -119,6 +287,12 @@ mod tests {
             "Weight should be 0.0-1.0. Got 1.5"
         );
         assert_eq!(t3.unwrap_err().to_string(), "Invalid weight: abcd");
+        assert!("text/html; level".parse::<MediaType>().is_err());
+        assert!(r#"text/html; level="1"#.parse::<MediaType>().is_err());
+        assert_eq!(
+            "*/xml".parse::<MediaType>().unwrap_err().to_string(),
+            "Invalid media range: */xml (only */* may have a wildcard type)"
+        );
     }
 
     #[test]
@@ -129,6 +303,55 @@ mod tests {
         assert!(t1 < t2);
         assert!(t1 < t3);
         assert!(t2 != t3);
+
+        let t4: MediaType = "text/html;level=1".parse().unwrap();
+        let t5: MediaType = "text/*".parse().unwrap();
+        let t6: MediaType = "*/*".parse().unwrap();
+        assert!(t4 > t3);
+        assert!(t3 > t5);
+        assert!(t5 > t6);
+        assert_ne!(t2.cmp(&t3), Ordering::Equal);
+    }
+
+    #[test]
+    fn media_type_order_should_be_total() {
+        let q = |s: &str| s.parse::<MediaType>().unwrap();
+        let at = |s: &str, position| MediaType { position, ..q(s) };
+
+        // the earlier of two equally weighted and specific ranges comes first
+        assert!(at("application/json", 0) > at("text/html", 1));
+        assert!(at("text/html", 0) > at("application/json", 1));
+        assert!(at("text/html;q=0.5", 0) < at("application/json", 1));
+
+        // parameter order doesn't matter, a missing weight differs from q=1
+        let (a, b) = (q("text/plain;a=1;b=2"), q("text/plain;b=2;a=1"));
+        assert_eq!(a, b);
+        assert_eq!(a.cmp(&b), Ordering::Equal);
+        assert_ne!(q("text/plain"), q("text/plain;q=1"));
+        assert_ne!(q("text/plain"), at("text/plain", 1));
+
+        let set: std::collections::HashSet<_> = [a, b, q("text/plain")].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn media_range_should_match() {
+        let all: MediaType = "*/*".parse().unwrap();
+        let text: MediaType = "text/*".parse().unwrap();
+        let html: MediaType = "text/html".parse().unwrap();
+
+        assert!(all.matches(&mime::APPLICATION_JSON));
+        assert!(text.matches(&mime::TEXT_PLAIN));
+        assert!(text.matches(&mime::TEXT_CSV));
+        assert!(!text.matches(&mime::APPLICATION_JSON));
+        assert!(html.matches(&mime::TEXT_HTML));
+        assert!(!html.matches(&mime::TEXT_PLAIN));
+
+        let level: MediaType = "text/html;level=1".parse().unwrap();
+        assert!(html.matches(&"text/html;level=1".parse().unwrap()));
+        assert!(level.matches(&"text/html;level=1;charset=utf-8".parse().unwrap()));
+        assert!(!level.matches(&mime::TEXT_HTML));
+        assert!(!level.matches(&"text/html;level=2".parse().unwrap()));
     }
 
     #[test]
@@ -0,0 +1,20 @@
+use crate::{AsOffer, MatchKind, MediaType, Negotiation};
+use mime::Mime;
+
+impl<'a, O: AsOffer> Negotiation<'a, O> {
+    /// The media type of the chosen offer, to be sent as `Content-Type`.
+    pub fn mime(&self) -> &'a Mime {
+        self.offer.mime()
+    }
+}
+
+impl MatchKind {
+    /// The kind of match a client media range makes.
+    pub(crate) fn of(range: &MediaType) -> Self {
+        match (range.mime.type_(), range.mime.subtype()) {
+            (mime::STAR, _) => Self::FullWildcard,
+            (_, mime::STAR) => Self::SubtypeWildcard,
+            _ => Self::Exact,
+        }
+    }
+}
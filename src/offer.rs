@@ -0,0 +1,136 @@
+use crate::{error::*, AsOffer, MediaType, Offer, QValue, QualityValue};
+use mime::Mime;
+use snafu::ensure;
+use std::{fmt, str::FromStr};
+
+impl Offer {
+    /// Create an offer with the given server quality, a thousandth from 0.0 to 1.0.
+    pub fn new(mime: Mime, quality: f32) -> Result<Self> {
+        Ok(Self {
+            mime,
+            quality: quality.try_into()?,
+        })
+    }
+}
+
+impl FromStr for Offer {
+    type Err = Error;
+
+    /// Parse an offer written like a media range, its `q` being the server quality,
+    /// e.g. `text/csv;q=0.5`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let media_type: MediaType = s.parse()?;
+        ensure!(
+            media_type.extensions.is_empty(),
+            ParameterSnafu { value: s }
+        );
+
+        Ok(Self {
+            quality: media_type.quality(),
+            mime: media_type.mime,
+        })
+    }
+}
+
+impl fmt::Display for Offer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.mime)?;
+
+        if self.quality != QValue::ONE {
+            write!(f, ";q={}", self.quality)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<Mime> for Offer {
+    fn from(mime: Mime) -> Self {
+        Self {
+            mime,
+            quality: QValue::ONE,
+        }
+    }
+}
+
+impl AsOffer for Offer {
+    fn mime(&self) -> &Mime {
+        &self.mime
+    }
+
+    fn quality(&self) -> QValue {
+        self.quality
+    }
+}
+
+impl AsOffer for Mime {
+    fn mime(&self) -> &Mime {
+        self
+    }
+}
+
+impl<T: AsOffer + ?Sized> AsOffer for &T {
+    fn mime(&self) -> &Mime {
+        (**self).mime()
+    }
+
+    fn quality(&self) -> QValue {
+        (**self).quality()
+    }
+}
+
+#[cfg(any(feature = "axum", feature = "axum07", feature = "actix"))]
+impl<R> crate::Renderers<R> {
+    /// Add an offer with its renderer.
+    pub(crate) fn push(&mut self, offer: Offer, render: R) {
+        self.offers.push(offer);
+        self.renderers.push(render);
+    }
+
+    /// Take the offer that `accept` prefers and its renderer, the first one added on a
+    /// tie.
+    pub(crate) fn choose(mut self, accept: &crate::Accept) -> Result<(Offer, R), NotAcceptable> {
+        let index = accept.negotiate_index(&self.offers)?;
+        Ok((
+            self.offers.swap_remove(index),
+            self.renderers.swap_remove(index),
+        ))
+    }
+}
+
+#[cfg(any(feature = "axum", feature = "axum07", feature = "actix"))]
+impl<R> Default for crate::Renderers<R> {
+    fn default() -> Self {
+        Self {
+            offers: Vec::new(),
+            renderers: Vec::new(),
+        }
+    }
+}
+
+#[cfg(any(feature = "axum", feature = "axum07", feature = "actix"))]
+impl<R> fmt::Debug for crate::Renderers<R> {
+    /// The offers only, as the renderers are closures.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(&self.offers).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offer_should_be_parsed_and_formatted() {
+        let offer: Offer = "text/csv; q=0.5".parse().unwrap();
+        assert_eq!(offer, Offer::new(mime::TEXT_CSV, 0.5).unwrap());
+        assert_eq!(offer.to_string(), "text/csv;q=0.5");
+
+        let offer: Offer = "text/html;charset=utf-8;q=1".parse().unwrap();
+        assert_eq!(offer.quality, QValue::ONE);
+        assert_eq!(offer.to_string(), "text/html;charset=utf-8");
+
+        assert!("text/html;q=0.5;ext".parse::<Offer>().is_err());
+        assert!("text/html;q=2".parse::<Offer>().is_err());
+    }
+}
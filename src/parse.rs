@@ -0,0 +1,251 @@
+use crate::{error::*, QValue};
+use snafu::{ensure, OptionExt};
+use std::borrow::Cow;
+
+/// Split a header into its list elements, dropping the empty ones (RFC 9110 §5.6.1.2).
+pub(crate) fn elements(s: &str) -> impl Iterator<Item = &str> {
+    split_quoted(s, b',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+}
+
+/// Split a `value[;q=weight]` list element, as used by the Accept-* headers other than
+/// Accept itself.
+pub(crate) fn parse_weighted(s: &str) -> Result<(&str, Option<QValue>)> {
+    let mut parts = split_quoted(s, b';');
+    let value = parts.next().unwrap_or_default().trim();
+
+    let mut weight = None;
+    for part in parts.filter(|part| !part.trim().is_empty()) {
+        match parse_param(part)? {
+            (name, Some(v)) if weight.is_none() && name.eq_ignore_ascii_case("q") => {
+                weight = Some(v.parse()?)
+            }
+            _ => return ParameterSnafu { value: part }.fail(),
+        }
+    }
+
+    Ok((value, weight))
+}
+
+/// Whether the values of a `Vary` header already cover the Accept header, listing
+/// `accept` or `*`.
+#[cfg(any(
+    feature = "axum",
+    feature = "axum07",
+    feature = "actix",
+    all(feature = "tower", any(feature = "http02", feature = "http1"))
+))]
+pub(crate) fn varies_on_accept<'a>(values: impl IntoIterator<Item = &'a [u8]>) -> bool {
+    values
+        .into_iter()
+        .filter_map(|value| std::str::from_utf8(value).ok())
+        .flat_map(elements)
+        .any(|name| name == "*" || name.eq_ignore_ascii_case("accept"))
+}
+
+/// Split `s` on `delim`, ignoring delimiters inside quoted strings (RFC 9110 §5.6.4).
+pub(crate) fn split_quoted(s: &str, delim: u8) -> Split<'_> {
+    Split {
+        s,
+        delim,
+        done: false,
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Split<'a> {
+    s: &'a str,
+    delim: u8,
+    done: bool,
+}
+
+impl<'a> Iterator for Split<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut quoted = false;
+        let mut escaped = false;
+        for (i, c) in self.s.bytes().enumerate() {
+            match c {
+                _ if escaped => escaped = false,
+                b'\\' if quoted => escaped = true,
+                b'"' => quoted = !quoted,
+                c if c == self.delim && !quoted => {
+                    let part = &self.s[..i];
+                    self.s = &self.s[i + 1..];
+                    return Some(part);
+                }
+                _ => {}
+            }
+        }
+
+        self.done = true;
+        Some(self.s)
+    }
+}
+
+/// Parse a `name[=value]` parameter. Quoted values are returned unescaped.
+pub(crate) fn parse_param(s: &str) -> Result<(&str, Option<String>)> {
+    let (name, value) = match s.split_once('=') {
+        Some((name, value)) => (name.trim(), Some(value.trim())),
+        None => (s.trim(), None),
+    };
+    ensure!(is_token(name), ParameterSnafu { value: s });
+
+    let value = match value {
+        Some(v) if v.starts_with('"') => Some(unquote(v).context(ParameterSnafu { value: s })?),
+        Some(v) => {
+            ensure!(is_token(v), ParameterSnafu { value: s });
+            Some(v.to_owned())
+        }
+        None => None,
+    };
+
+    Ok((name, value))
+}
+
+/// Quote `value` as a quoted-string unless it is a valid token.
+pub(crate) fn quote(value: &str) -> Cow<'_, str> {
+    if is_token(value) {
+        return Cow::Borrowed(value);
+    }
+
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    Cow::Owned(quoted)
+}
+
+/// Quote a parameter value for the source of a `Mime`, which can't hold a `"` in a
+/// quoted-string, escaped or not.
+pub(crate) fn quote_mime_param(value: &str) -> Result<Cow<'_, str>> {
+    ensure!(!value.contains('"'), UnsupportedParameterSnafu { value });
+    Ok(quote(value))
+}
+
+/// Strip the quotes and escapes of a quoted-string. `None` if `s` isn't one.
+fn unquote(s: &str) -> Option<String> {
+    let inner = s.strip_prefix('"')?;
+    let mut value = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => value.push(chars.next()?),
+            '"' => return chars.as_str().is_empty().then_some(value),
+            c => value.push(c),
+        }
+    }
+
+    None
+}
+
+/// Undo the escapes of a quoted-string without its quotes, as in the parameter values
+/// of a `Mime`.
+#[cfg(feature = "serde")]
+pub(crate) fn unescape(value: &str) -> Cow<'_, str> {
+    if !value.contains('\\') {
+        return Cow::Borrowed(value);
+    }
+
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+    Cow::Owned(unescaped)
+}
+
+/// `token = 1*tchar` as defined in RFC 9110 §5.6.2.
+pub(crate) fn is_token(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|c| c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_quoted_should_respect_quotes() {
+        let parts: Vec<_> = split_quoted(r#"a;b="x;y";c="\";";d"#, b';').collect();
+        assert_eq!(parts, vec!["a", r#"b="x;y""#, r#"c="\";""#, "d"]);
+
+        let parts: Vec<_> = split_quoted("", b';').collect();
+        assert_eq!(parts, vec![""]);
+
+        // the examples from RFC 9110 §5.5
+        let parts: Vec<_> = split_quoted(
+            r#""http://example.com/a.html,foo", "http://without-a-comma.example.com/""#,
+            b',',
+        )
+        .map(str::trim)
+        .collect();
+        assert_eq!(
+            parts,
+            vec![
+                r#""http://example.com/a.html,foo""#,
+                r#""http://without-a-comma.example.com/""#
+            ]
+        );
+
+        let parts: Vec<_> = split_quoted(r#""Sat, 04 May 1996", "Wed, 14 Sep 2005""#, b',')
+            .map(str::trim)
+            .collect();
+        assert_eq!(
+            parts,
+            vec![r#""Sat, 04 May 1996""#, r#""Wed, 14 Sep 2005""#]
+        );
+    }
+
+    #[test]
+    fn param_should_be_parsed() {
+        assert_eq!(
+            parse_param("level=1").unwrap(),
+            ("level", Some("1".to_owned()))
+        );
+        assert_eq!(
+            parse_param(r#" profile="a\;b, \"c\"" "#).unwrap(),
+            ("profile", Some(r#"a;b, "c""#.to_owned()))
+        );
+        assert_eq!(parse_param("ext").unwrap(), ("ext", None));
+        assert!(parse_param(r#"a="b"#).is_err());
+        assert!(parse_param(r#"a="b"c"#).is_err());
+        assert!(parse_param("a=b c").is_err());
+        assert!(parse_param("=b").is_err());
+    }
+
+    #[test]
+    fn weighted_element_should_be_parsed() {
+        assert_eq!(parse_weighted("gzip").unwrap(), ("gzip", None));
+        assert_eq!(
+            parse_weighted("en-US ; Q=0.5").unwrap(),
+            ("en-US", "0.5".parse().ok())
+        );
+        assert!(parse_weighted("en;level=1").is_err());
+        assert!(parse_weighted("en;q=0.5;q=0.4").is_err());
+        assert!(parse_weighted("en;q=2").is_err());
+    }
+
+    #[test]
+    fn value_should_be_quoted_when_needed() {
+        assert_eq!(quote("utf-8"), "utf-8");
+        assert_eq!(quote("a;b"), r#""a;b""#);
+        assert_eq!(quote(r#"a"b\"#), r#""a\"b\\""#);
+        assert_eq!(quote(""), r#""""#);
+    }
+}
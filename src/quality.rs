@@ -0,0 +1,134 @@
+use crate::{
+    error::*,
+    parse::{elements, parse_weighted},
+    QValue, QualityItem, QualityList, QualityValue,
+};
+use itertools::Itertools;
+use std::{cmp::Reverse, fmt, str::FromStr};
+
+impl<T: QualityValue> QualityList<T> {
+    /// Parse a header, skipping the malformed elements instead of failing on the first
+    /// one. The errors of the skipped elements are returned alongside.
+    pub fn parse_lenient(s: &str) -> (Self, Vec<Error>) {
+        let (items, errors) = Self::parse_elements_lenient(s);
+        (items.into_iter().collect(), errors)
+    }
+
+    /// Parse the elements of a header in header order, before they get sorted.
+    pub(crate) fn parse_elements(s: &str) -> Result<Vec<T>> {
+        elements(s).map(str::parse).collect()
+    }
+
+    /// Parse the elements of a header in header order, skipping the malformed ones.
+    pub(crate) fn parse_elements_lenient(s: &str) -> (Vec<T>, Vec<Error>) {
+        let mut errors = Vec::new();
+        let items = elements(s)
+            .filter_map(|part| part.parse().map_err(|e| errors.push(e)).ok())
+            .collect();
+
+        (items, errors)
+    }
+}
+
+impl<T> Default for QualityList<T> {
+    fn default() -> Self {
+        Self { items: Vec::new() }
+    }
+}
+
+impl<T: QualityValue> FromStr for QualityList<T> {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_elements(s).map(Self::from_iter)
+    }
+}
+
+impl<T: QualityValue> FromIterator<T> for QualityList<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut items: Vec<_> = iter.into_iter().collect();
+        items.sort_by_key(|item| Reverse(item.quality()));
+
+        Self { items }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for QualityList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.items.iter().join(", "))
+    }
+}
+
+impl<T> QualityValue for QualityItem<T>
+where
+    T: FromStr + fmt::Display,
+    T::Err: Into<Error>,
+{
+    fn weight(&self) -> Option<QValue> {
+        self.weight
+    }
+}
+
+impl<T> FromStr for QualityItem<T>
+where
+    T: FromStr,
+    T::Err: Into<Error>,
+{
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (value, weight) = parse_weighted(s)?;
+
+        Ok(Self {
+            value: value.parse().map_err(Into::into)?,
+            weight,
+        })
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for QualityItem<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value)?;
+
+        if let Some(weight) = self.weight {
+            write!(f, ";q={weight}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Te = QualityList<QualityItem<String>>;
+
+    #[test]
+    fn quality_list_should_be_parsed_and_sorted() {
+        let te: Te = "deflate;q=0.5, , trailers, gzip;q=0.5, chunked;q=1"
+            .parse()
+            .unwrap();
+
+        assert_eq!(te.items[0].value, "trailers");
+        assert_eq!(te.items[0].weight, None);
+        assert_eq!(te.items[1].value, "chunked");
+        assert_eq!(te.items[1].quality(), QValue::ONE);
+        assert_eq!(
+            te.to_string(),
+            "trailers, chunked;q=1, deflate;q=0.5, gzip;q=0.5"
+        );
+        assert_eq!("".parse::<Te>().unwrap(), Te::default());
+    }
+
+    #[test]
+    fn quality_list_should_be_parsed_leniently() {
+        assert!("gzip, deflate;q=2".parse::<Te>().is_err());
+
+        let (te, errors) = Te::parse_lenient("gzip;q=0.5, deflate;q=2, br;x=1, zstd");
+        assert_eq!(te.to_string(), "zstd, gzip;q=0.5");
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].to_string(), "Weight should be 0.0-1.0. Got 2");
+        assert_eq!(errors[1].to_string(), "Invalid parameter: x=1");
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{error::*, QValue};
+use snafu::{ensure, OptionExt};
+use std::{fmt, ops::Mul, str::FromStr};
+
+impl QValue {
+    /// `q=0`: not acceptable.
+    pub const ZERO: QValue = QValue(0);
+    /// `q=1`: the most preferred, and the weight of an element without `q`.
+    pub const ONE: QValue = QValue(1000);
+
+    /// Create a q-value from thousandths, e.g. 500 for 0.5. `None` above 1000.
+    pub const fn from_thousandths(value: u16) -> Option<Self> {
+        if value <= 1000 {
+            Some(Self(value))
+        } else {
+            None
+        }
+    }
+
+    /// The q-value in thousandths, e.g. 500 for 0.5.
+    pub const fn as_thousandths(self) -> u16 {
+        self.0
+    }
+
+    /// Whether this q-value makes an element not acceptable.
+    pub const fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl Default for QValue {
+    fn default() -> Self {
+        Self::ONE
+    }
+}
+
+impl FromStr for QValue {
+    type Err = Error;
+
+    /// Parse a q-value with the strict grammar of RFC 9110 §12.4.2:
+    /// `( "0" [ "." 0*3DIGIT ] ) / ( "1" [ "." 0*3("0") ] )`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+        let valid =
+            matches!(int, "0" | "1") && frac.len() <= 3 && frac.bytes().all(|c| c.is_ascii_digit());
+
+        if !valid {
+            // tell an out of range number from garbage
+            ensure!(
+                s.parse::<f64>().map_or(true, |v| (0.0..=1.0).contains(&v)),
+                WeightRangeSnafu { value: s }
+            );
+            return ParseWeightSnafu { value: s }.fail();
+        }
+
+        let frac = frac
+            .bytes()
+            .chain(std::iter::repeat(b'0'))
+            .take(3)
+            .fold(0, |acc, c| acc * 10 + u16::from(c - b'0'));
+        let value = if int == "1" { 1000 + frac } else { frac };
+
+        Self::from_thousandths(value).context(WeightRangeSnafu { value: s })
+    }
+}
+
+impl TryFrom<f32> for QValue {
+    type Error = Error;
+
+    /// Take a float that is a thousandth, within `f32` precision: `0.1234` or `0.0004`
+    /// fail with [`Error::ParseWeight`] like in a header, instead of being rounded.
+    fn try_from(value: f32) -> Result<Self, Self::Error> {
+        let text = || value.to_string();
+        ensure!(value.is_finite(), ParseWeightSnafu { value: text() });
+        ensure!(
+            (0.0..=1.0).contains(&value),
+            WeightRangeSnafu { value: text() }
+        );
+
+        let thousandths = value * 1000.0;
+        let rounded = thousandths.round();
+        ensure!(
+            (thousandths - rounded).abs() <= 1e-4 && (rounded > 0.0 || value == 0.0),
+            ParseWeightSnafu { value: text() }
+        );
+
+        Ok(Self(rounded as u16))
+    }
+}
+
+impl From<QValue> for f32 {
+    fn from(q: QValue) -> Self {
+        f32::from(q.0) / 1000.0
+    }
+}
+
+impl Mul for QValue {
+    type Output = QValue;
+
+    /// Multiply two q-values, rounding to the nearest thousandth. A product of non-zero
+    /// q-values is never rounded down to zero.
+    fn mul(self, rhs: Self) -> Self::Output {
+        let product = (u32::from(self.0) * u32::from(rhs.0) + 500) / 1000;
+        match product {
+            0 if !self.is_zero() && !rhs.is_zero() => Self(1),
+            product => Self(product as u16),
+        }
+    }
+}
+
+impl fmt::Display for QValue {
+    /// The shortest form of the q-value: `1`, `0.5`, `0.25`, `0`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            1000 => write!(f, "1"),
+            0 => write!(f, "0"),
+            v => {
+                let frac = format!("{v:03}");
+                write!(f, "0.{}", frac.trim_end_matches('0'))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn qvalue_should_be_parsed() {
+        let cases = [
+            ("0", 0),
+            ("0.", 0),
+            ("0.5", 500),
+            ("0.05", 50),
+            ("0.123", 123),
+            ("1", 1000),
+            ("1.0", 1000),
+            ("1.000", 1000),
+        ];
+        for (s, v) in cases {
+            assert_eq!(s.parse::<QValue>().unwrap().as_thousandths(), v, "{s}");
+        }
+
+        for s in ["0.1234", ".5", "00.5", "0,5", "abcd", "", " 1", "+1"] {
+            assert!(
+                matches!(s.parse::<QValue>(), Err(Error::ParseWeight { .. })),
+                "{s}"
+            );
+        }
+        for s in ["1.001", "1.5", "-0.5", "2"] {
+            assert!(
+                matches!(s.parse::<QValue>(), Err(Error::WeightRange { .. })),
+                "{s}"
+            );
+        }
+    }
+
+    #[test]
+    fn qvalue_should_be_formatted_canonically() {
+        let cases = [
+            ("0.000", "0"),
+            ("0.3", "0.3"),
+            ("0.300", "0.3"),
+            ("0.25", "0.25"),
+            ("0.001", "0.001"),
+            ("1.00", "1"),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(s.parse::<QValue>().unwrap().to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn qvalue_should_be_ordered_and_multiplied() {
+        let q = |s: &str| s.parse::<QValue>().unwrap();
+
+        assert!(q("0.3") < q("0.301"));
+        assert_eq!(q("0.30"), q("0.3"));
+        assert_eq!(QValue::default(), QValue::ONE);
+        assert_eq!(q("0.8") * q("0.5"), q("0.4"));
+        assert_eq!(q("0.333") * q("0.5"), q("0.167"));
+        assert_eq!(q("0.001") * q("0.001"), q("0.001"));
+        assert_eq!(q("0.5") * QValue::ZERO, QValue::ZERO);
+        assert_eq!(QValue::try_from(0.3).unwrap(), q("0.3"));
+        assert_eq!(f32::from(q("0.25")), 0.25);
+        assert_eq!(QValue::try_from(0.001).unwrap().as_thousandths(), 1);
+        assert_eq!(QValue::try_from(0.123).unwrap().as_thousandths(), 123);
+        assert!(matches!(
+            QValue::try_from(1.1),
+            Err(Error::WeightRange { .. })
+        ));
+        for invalid in [0.1234, 0.0004, 1e-9, f32::NAN, f32::INFINITY] {
+            assert!(
+                matches!(QValue::try_from(invalid), Err(Error::ParseWeight { .. })),
+                "{invalid}"
+            );
+        }
+        assert!(QValue::from_thousandths(1001).is_none());
+    }
+}
@@ -0,0 +1,416 @@
+use crate::{
+    error::*,
+    parse::{is_token, quote_mime_param, unescape},
+    Accept, Charset, Coding, LanguageRange, MediaType, Offer, QValue, QualityItem, QualityList,
+    QualityValue, Structured,
+};
+use serde::{de, de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+use snafu::ensure;
+use std::{collections::BTreeMap, fmt, fmt::Write, marker::PhantomData, str::FromStr};
+
+/// Serialize the types as their header string, with `Display` and `FromStr`.
+macro_rules! impl_header_string {
+    ($($ty:ty),*) => {
+        $(
+            impl Serialize for $ty {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_str(self)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $ty {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    deserializer.deserialize_str(FromStrVisitor(PhantomData))
+                }
+            }
+        )*
+    };
+}
+
+impl_header_string!(
+    Accept,
+    MediaType,
+    QValue,
+    Charset,
+    Coding,
+    LanguageRange,
+    Offer
+);
+
+impl<T: fmt::Display> Serialize for QualityList<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de, T: QualityValue> Deserialize<'de> for QualityList<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(FromStrVisitor(PhantomData))
+    }
+}
+
+impl<T: fmt::Display> Serialize for QualityItem<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for QualityItem<T>
+where
+    T: FromStr,
+    T::Err: Into<Error>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(FromStrVisitor(PhantomData))
+    }
+}
+
+struct FromStrVisitor<T>(PhantomData<T>);
+
+impl<'de, T> de::Visitor<'de> for FromStrVisitor<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a header string")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        v.parse().map_err(E::custom)
+    }
+}
+
+/// A type with an expanded serde form, used by [`Structured`].
+pub trait Expand: Sized {
+    /// The expanded form.
+    type Expanded: Serialize + DeserializeOwned;
+
+    /// Expand the value.
+    fn expand(&self) -> Self::Expanded;
+
+    /// Build the value back from its expanded form.
+    fn collapse(expanded: Self::Expanded) -> Result<Self>;
+}
+
+impl<T: Expand> Serialize for Structured<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.expand().serialize(serializer)
+    }
+}
+
+impl<'de, T: Expand> Deserialize<'de> for Structured<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let expanded = T::Expanded::deserialize(deserializer)?;
+        T::collapse(expanded)
+            .map(Structured)
+            .map_err(de::Error::custom)
+    }
+}
+
+impl Expand for QValue {
+    type Expanded = f64;
+
+    fn expand(&self) -> f64 {
+        f64::from(self.as_thousandths()) / 1000.0
+    }
+
+    /// Only exact thousandths are taken, like in a header: `0.1234` is not rounded.
+    fn collapse(expanded: f64) -> Result<Self> {
+        let value = || expanded.to_string();
+        ensure!(
+            (0.0..=1.0).contains(&expanded),
+            WeightRangeSnafu { value: value() }
+        );
+        let thousandths = (expanded * 1000.0).round();
+        ensure!(
+            thousandths / 1000.0 == expanded,
+            ParseWeightSnafu { value: value() }
+        );
+
+        Ok(QValue::from_thousandths(thousandths as u16).expect("checked to be in range"))
+    }
+}
+
+/// A media range, or an offer without `extensions`, as `{mime, q, params, extensions}`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExpandedMediaType {
+    mime: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    q: Option<f64>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    params: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    extensions: Vec<(String, Option<String>)>,
+}
+
+impl Expand for MediaType {
+    type Expanded = ExpandedMediaType;
+
+    fn expand(&self) -> ExpandedMediaType {
+        ExpandedMediaType {
+            mime: self.mime.essence_str().to_owned(),
+            q: self.weight.as_ref().map(Expand::expand),
+            params: self
+                .mime
+                .params()
+                .map(|(name, value)| (name.as_str().to_owned(), unescape(value.as_str()).into()))
+                .collect(),
+            extensions: self.extensions.clone(),
+        }
+    }
+
+    fn collapse(expanded: ExpandedMediaType) -> Result<Self> {
+        let mut source = expanded.mime;
+        for (name, value) in &expanded.params {
+            write!(source, ";{name}={}", quote_mime_param(value)?).expect("write to string");
+        }
+
+        // a `q` in `params` would be taken for the weight
+        let media_type: MediaType = source.parse()?;
+        ensure!(
+            media_type.weight.is_none(),
+            ParameterSnafu { value: source }
+        );
+        for (name, _) in &expanded.extensions {
+            ensure!(is_token(name), ParameterSnafu { value: name });
+        }
+
+        Ok(MediaType {
+            weight: expanded.q.map(Expand::collapse).transpose()?,
+            extensions: expanded.extensions,
+            ..media_type
+        })
+    }
+}
+
+impl Expand for Accept {
+    type Expanded = Vec<ExpandedMediaType>;
+
+    fn expand(&self) -> Self::Expanded {
+        self.types.iter().map(Expand::expand).collect()
+    }
+
+    fn collapse(expanded: Self::Expanded) -> Result<Self> {
+        expanded.into_iter().map(Expand::collapse).collect()
+    }
+}
+
+impl Expand for Offer {
+    type Expanded = ExpandedMediaType;
+
+    /// The server quality is the `q`, like in [`Offer::from_str`].
+    fn expand(&self) -> ExpandedMediaType {
+        let weight = (self.quality != QValue::ONE).then_some(self.quality);
+        MediaType {
+            mime: self.mime.clone(),
+            weight,
+            extensions: Vec::new(),
+            position: 0,
+        }
+        .expand()
+    }
+
+    fn collapse(expanded: ExpandedMediaType) -> Result<Self> {
+        let media_type = MediaType::collapse(expanded)?;
+        ensure!(
+            media_type.extensions.is_empty(),
+            ParameterSnafu {
+                value: media_type.to_string()
+            }
+        );
+
+        Ok(Self {
+            quality: media_type.quality(),
+            mime: media_type.mime,
+        })
+    }
+}
+
+/// A list element as `{value, q}`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExpandedItem {
+    value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    q: Option<f64>,
+}
+
+impl<T> Expand for QualityItem<T>
+where
+    T: FromStr + fmt::Display,
+    T::Err: Into<Error>,
+{
+    type Expanded = ExpandedItem;
+
+    fn expand(&self) -> ExpandedItem {
+        ExpandedItem {
+            value: self.value.to_string(),
+            q: self.weight.as_ref().map(Expand::expand),
+        }
+    }
+
+    fn collapse(expanded: ExpandedItem) -> Result<Self> {
+        Ok(Self {
+            value: expanded.value.parse().map_err(Into::into)?,
+            weight: expanded.q.map(Expand::collapse).transpose()?,
+        })
+    }
+}
+
+impl<T: Expand + QualityValue> Expand for QualityList<T> {
+    type Expanded = Vec<T::Expanded>;
+
+    fn expand(&self) -> Self::Expanded {
+        self.items.iter().map(Expand::expand).collect()
+    }
+
+    fn collapse(expanded: Self::Expanded) -> Result<Self> {
+        expanded.into_iter().map(Expand::collapse).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AcceptEncoding, AcceptLanguage, MatchKind};
+    use serde_json::{from_value, json, to_value, Value};
+
+    fn round_trip<T>(value: &T) -> Value
+    where
+        T: Serialize + DeserializeOwned + PartialEq + fmt::Debug,
+    {
+        let json = to_value(value).unwrap();
+        assert_eq!(&from_value::<T>(json.clone()).unwrap(), value);
+        json
+    }
+
+    const HEADER: &str = r#"text/plain;q=0.5, text/html;level=1;x="a\\b", application/ld+json;profile="a, b";q=0.5;ext, */*;q=0"#;
+
+    #[test]
+    fn types_should_round_trip_as_header_strings() {
+        let accept: Accept = HEADER.parse().unwrap();
+        assert_eq!(
+            round_trip(&accept),
+            r#"text/html;level=1;x="a\\b", application/ld+json;profile="a, b";q=0.5;ext, text/plain;q=0.5, */*;q=0"#
+        );
+        for range in &accept.types {
+            let json = to_value(range).unwrap();
+            let back: MediaType = from_value(json).unwrap();
+            assert_eq!(back.position, 0);
+            let back = MediaType {
+                position: range.position,
+                ..back
+            };
+            assert_eq!(&back, range);
+        }
+        assert_eq!(round_trip(&Accept::any()), "");
+        let media_type: MediaType = "text/plain;format=flowed;q=0.8".parse().unwrap();
+        assert_eq!(round_trip(&media_type), "text/plain;format=flowed;q=0.8");
+
+        let encoding: AcceptEncoding = "gzip;q=0.8, br".parse().unwrap();
+        assert_eq!(round_trip(&encoding), "br, gzip;q=0.8");
+        assert_eq!(round_trip(&encoding.items[1]), "gzip;q=0.8");
+        let language: AcceptLanguage = "en-US, fr;q=0.25".parse().unwrap();
+        assert_eq!(round_trip(&language.items[1].value), "fr");
+
+        assert_eq!(round_trip(&"0.25".parse::<QValue>().unwrap()), "0.25");
+        assert_eq!(
+            round_trip(&"text/csv;q=0.5".parse::<Offer>().unwrap()),
+            "text/csv;q=0.5"
+        );
+        assert_eq!(round_trip(&MatchKind::SubtypeWildcard), "subtype_wildcard");
+
+        assert!(from_value::<Accept>(json!("text/html;q=2")).is_err());
+        assert!(from_value::<QValue>(json!(0.5)).is_err());
+    }
+
+    #[test]
+    fn types_should_round_trip_in_structured_form() {
+        let accept: Accept = HEADER.parse().unwrap();
+        let json = round_trip(&Structured(accept.clone()));
+        assert_eq!(
+            json,
+            json!([
+                {"mime": "text/html", "params": {"level": "1", "x": "a\\b"}},
+                {
+                    "mime": "application/ld+json",
+                    "q": 0.5,
+                    "params": {"profile": "a, b"},
+                    "extensions": [["ext", null]]
+                },
+                {"mime": "text/plain", "q": 0.5},
+                {"mime": "*/*", "q": 0.0}
+            ])
+        );
+        for range in &accept.types {
+            let json = to_value(Structured(range.clone())).unwrap();
+            let Structured(back) = from_value::<Structured<MediaType>>(json).unwrap();
+            assert_eq!(back.position, 0);
+            let back = MediaType {
+                position: range.position,
+                ..back
+            };
+            assert_eq!(&back, range);
+        }
+
+        let encoding: AcceptEncoding = "gzip;q=0.8, br".parse().unwrap();
+        let json = round_trip(&Structured(encoding));
+        assert_eq!(json, json!([{"value": "br"}, {"value": "gzip", "q": 0.8}]));
+        assert_eq!(round_trip(&Structured(QValue::ONE)), json!(1.0));
+        for offer in ["text/csv;q=0.5", "text/html;charset=utf-8"] {
+            let offer: Offer = offer.parse().unwrap();
+            round_trip(&Structured(offer));
+        }
+        let json = to_value(Structured("text/csv;q=0.5".parse::<Offer>().unwrap())).unwrap();
+        assert_eq!(json, json!({"mime": "text/csv", "q": 0.5}));
+        assert!(from_value::<Structured<Offer>>(json!({
+            "mime": "text/csv",
+            "extensions": [["ext", null]]
+        }))
+        .is_err());
+        for (value, thousandths) in [
+            (json!(0), 0),
+            (json!(0.001), 1),
+            (json!(0.25), 250),
+            (json!(0.999), 999),
+            (json!(1), 1000),
+        ] {
+            let Structured(q) = from_value::<Structured<QValue>>(value).unwrap();
+            assert_eq!(q.as_thousandths(), thousandths);
+        }
+        for (invalid, error) in [
+            (json!(0.1234), "Invalid weight: 0.1234"),
+            (json!(0.0005), "Invalid weight: 0.0005"),
+            (json!(1.5), "Weight should be 0.0-1.0. Got 1.5"),
+            (json!(-0.5), "Weight should be 0.0-1.0. Got -0.5"),
+        ] {
+            let err = from_value::<Structured<QValue>>(invalid).unwrap_err();
+            assert_eq!(err.to_string(), error);
+        }
+
+        // as written by hand, e.g. in YAML config
+        let Structured(accept) = from_value::<Structured<Accept>>(json!([
+            {"mime": "*/*", "q": 0},
+            {"mime": "application/json"}
+        ]))
+        .unwrap();
+        assert_eq!(accept.to_string(), "application/json, */*;q=0");
+
+        for invalid in [
+            json!([{"mime": "*/json"}]),
+            json!([{"mime": "text/html", "q": 1.5}]),
+            json!([{"mime": "text/html", "params": {"q": "0.5"}}]),
+            json!([{"mime": "text/html", "extensions": [["a b", null]]}]),
+            json!([{"mime": "text/html", "level": "1"}]),
+        ] {
+            assert!(
+                from_value::<Structured<Accept>>(invalid.clone()).is_err(),
+                "{invalid}"
+            );
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use crate::{Negotiate, NegotiateLayer, Offer};
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+
+impl NegotiateLayer {
+    /// Negotiate against `offers`, the first one winning a tie.
+    pub fn new<O: Into<Offer>>(offers: impl IntoIterator<Item = O>) -> Self {
+        Self {
+            offers: offers.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl<S> Layer<S> for NegotiateLayer {
+    type Service = Negotiate<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Negotiate {
+            inner,
+            offers: self.offers.clone(),
+        }
+    }
+}
+
+impl<S> Negotiate<S> {
+    /// Wrap `inner`, negotiating against `offers`.
+    pub fn new<O: Into<Offer>>(inner: S, offers: impl IntoIterator<Item = O>) -> Self {
+        NegotiateLayer::new(offers).layer(inner)
+    }
+}
+
+pin_project! {
+    /// The response future of [`Negotiate`].
+    #[derive(Debug)]
+    pub struct NegotiateFuture<F, R> {
+        #[pin]
+        state: State<F, R>,
+    }
+}
+
+pin_project! {
+    #[project = StateProj]
+    #[derive(Debug)]
+    enum State<F, R> {
+        Inner { #[pin] future: F },
+        NotAcceptable { response: Option<R> },
+    }
+}
+
+/// The `Service` of [`Negotiate`] for the requests and responses of the given `http`
+/// crate.
+macro_rules! impl_http {
+    ($http:ident, $module:ident, $tests:ident) => {
+        mod $module {
+            use super::{State, StateProj};
+            use crate::{parse::varies_on_accept, Negotiate, NegotiateFuture};
+            use std::{
+                future::Future,
+                pin::Pin,
+                task::{Context, Poll},
+            };
+            use tower_service::Service;
+            use $http::{header::VARY, HeaderValue};
+
+            impl<S, ReqBody, ResBody> Service<$http::Request<ReqBody>> for Negotiate<S>
+            where
+                S: Service<$http::Request<ReqBody>, Response = $http::Response<ResBody>>,
+                ResBody: Default,
+            {
+                type Response = $http::Response<ResBody>;
+                type Error = S::Error;
+                type Future = NegotiateFuture<S::Future, Self::Response>;
+
+                fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+                    self.inner.poll_ready(cx)
+                }
+
+                /// Malformed media ranges in the Accept header are skipped.
+                fn call(&mut self, mut req: $http::Request<ReqBody>) -> Self::Future {
+                    let (accept, _) = crate::Accept::from_headers_lenient(req.headers());
+                    let state = match accept.negotiate(&self.offers) {
+                        Ok(negotiated) => {
+                            let mime = negotiated.mime().clone();
+                            req.extensions_mut().insert(mime);
+                            State::Inner {
+                                future: self.inner.call(req),
+                            }
+                        }
+                        Err(_) => {
+                            let mut response = $http::Response::new(ResBody::default());
+                            *response.status_mut() = $http::StatusCode::NOT_ACCEPTABLE;
+                            State::NotAcceptable {
+                                response: Some(response),
+                            }
+                        }
+                    };
+
+                    NegotiateFuture { state }
+                }
+            }
+
+            impl<F, B, E> Future for NegotiateFuture<F, $http::Response<B>>
+            where
+                F: Future<Output = Result<$http::Response<B>, E>>,
+            {
+                type Output = Result<$http::Response<B>, E>;
+
+                fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+                    let mut response = match self.project().state.project() {
+                        StateProj::Inner { future } => match future.poll(cx) {
+                            Poll::Ready(Ok(response)) => response,
+                            other => return other,
+                        },
+                        StateProj::NotAcceptable { response } => {
+                            response.take().expect("polled after completion")
+                        }
+                    };
+
+                    let headers = response.headers_mut();
+                    if !varies_on_accept(headers.get_all(VARY).iter().map(HeaderValue::as_bytes)) {
+                        headers.append(VARY, HeaderValue::from_static("accept"));
+                    }
+                    Poll::Ready(Ok(response))
+                }
+            }
+        }
+
+        #[cfg(test)]
+        mod $tests {
+            use crate::{NegotiateLayer, Offer};
+            use mime::Mime;
+            use std::convert::Infallible;
+            use tower::{service_fn, ServiceExt};
+            use tower_layer::Layer;
+            use $http::{
+                header::{ACCEPT, CONTENT_TYPE, VARY},
+                Request, Response, StatusCode,
+            };
+
+            async fn call(accept: Option<&str>) -> Response<String> {
+                let service = NegotiateLayer::new([
+                    Offer::from(mime::APPLICATION_JSON),
+                    Offer::new(mime::TEXT_HTML, 0.5).unwrap(),
+                ])
+                .layer(service_fn(|req: Request<()>| async move {
+                    let mime = req.extensions().get::<Mime>().unwrap();
+                    let response = Response::builder()
+                        .header(CONTENT_TYPE, mime.as_ref())
+                        .body(String::new())
+                        .unwrap();
+                    Ok::<_, Infallible>(response)
+                }));
+
+                let mut req = Request::builder();
+                if let Some(accept) = accept {
+                    req = req.header(ACCEPT, accept);
+                }
+                service.oneshot(req.body(()).unwrap()).await.unwrap()
+            }
+
+            #[tokio::test]
+            async fn request_should_be_negotiated() {
+                let cases = [
+                    (None, "application/json"),
+                    (Some("text/html, application/json;q=0.4"), "text/html"),
+                    (Some("text/*, foo, */*;q=0.1"), "text/html"),
+                ];
+                for (accept, expected) in cases {
+                    let response = call(accept).await;
+                    assert_eq!(response.status(), StatusCode::OK);
+                    assert_eq!(response.headers()[CONTENT_TYPE], expected, "{accept:?}");
+                    assert_eq!(response.headers()[VARY], "accept");
+                }
+            }
+
+            #[tokio::test]
+            async fn vary_should_list_accept_once() {
+                for (vary, expected) in [
+                    (None, vec!["accept"]),
+                    (Some("origin"), vec!["origin", "accept"]),
+                    (Some("Origin, Accept"), vec!["Origin, Accept"]),
+                    (Some("*"), vec!["*"]),
+                ] {
+                    let service = NegotiateLayer::new([mime::TEXT_HTML]).layer(service_fn(
+                        move |_: Request<()>| async move {
+                            let mut response = Response::builder();
+                            if let Some(vary) = vary {
+                                response = response.header(VARY, vary);
+                            }
+                            Ok::<_, Infallible>(response.body(String::new()).unwrap())
+                        },
+                    ));
+
+                    let response = service.oneshot(Request::new(())).await.unwrap();
+                    let values: Vec<_> = response.headers().get_all(VARY).iter().collect();
+                    assert_eq!(values, expected, "{vary:?}");
+                }
+            }
+
+            #[tokio::test]
+            async fn unacceptable_request_should_be_rejected() {
+                let response = call(Some("image/png, application/json;q=0")).await;
+                assert_eq!(response.status(), StatusCode::NOT_ACCEPTABLE);
+                assert_eq!(response.headers()[VARY], "accept");
+                assert!(response.headers().get(CONTENT_TYPE).is_none());
+            }
+        }
+    };
+}
+
+#[cfg(feature = "http02")]
+impl_http!(http02, http02_service, http02_tests);
+#[cfg(feature = "http1")]
+impl_http!(http1, http1_service, http1_tests);
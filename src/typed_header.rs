@@ -0,0 +1,67 @@
+//! `headers::Header` for `Accept`, for each supported version of the `headers` crate.
+
+macro_rules! impl_header {
+    ($headers:ident, $http:ident, $tests:ident) => {
+        impl $headers::Header for crate::Accept {
+            fn name() -> &'static $http::HeaderName {
+                &$http::header::ACCEPT
+            }
+
+            /// Decode every Accept field line, combined as one list.
+            fn decode<'i, I>(values: &mut I) -> Result<Self, $headers::Error>
+            where
+                I: Iterator<Item = &'i $http::HeaderValue>,
+            {
+                Self::from_values(
+                    values.map($http::HeaderValue::as_bytes),
+                    crate::NonUtf8::Reject,
+                )
+                .map_err(|_| $headers::Error::invalid())
+            }
+
+            fn encode<E: Extend<$http::HeaderValue>>(&self, values: &mut E) {
+                let value = $http::HeaderValue::from_bytes(self.to_string().as_bytes())
+                    .expect("Accept is formatted as a valid header value");
+                values.extend(std::iter::once(value));
+            }
+        }
+
+        #[cfg(test)]
+        mod $tests {
+            use crate::Accept;
+            use $headers::HeaderMapExt;
+            use $http::{header::ACCEPT, HeaderMap, HeaderValue};
+
+            #[test]
+            fn accept_should_be_decoded_from_every_value() {
+                let mut headers = HeaderMap::new();
+                headers.append(ACCEPT, HeaderValue::from_static("text/html, text/*;q=0.5"));
+                headers.append(ACCEPT, HeaderValue::from_static("application/json;q=0.8"));
+
+                let accept: Accept = headers.typed_get().unwrap();
+                assert_eq!(
+                    accept.to_string(),
+                    "text/html, application/json;q=0.8, text/*;q=0.5"
+                );
+
+                headers.append(ACCEPT, HeaderValue::from_static("foo"));
+                assert!(headers.typed_try_get::<Accept>().is_err());
+            }
+
+            #[test]
+            fn accept_should_be_encoded() {
+                let accept: Accept = "application/json;q=0.8, text/html;level=1".parse().unwrap();
+                let mut headers = HeaderMap::new();
+                headers.typed_insert(accept.clone());
+
+                assert_eq!(headers[ACCEPT], "text/html;level=1, application/json;q=0.8");
+                assert_eq!(headers.typed_get(), Some(accept));
+            }
+        }
+    };
+}
+
+#[cfg(feature = "headers")]
+impl_header!(headers, http02, headers03_tests);
+#[cfg(feature = "headers04")]
+impl_header!(headers04, http1, headers04_tests);